@@ -3,6 +3,7 @@ use bevy::asset::{AssetLoader, LoadContext};
 use bevy::asset::io::Reader;
 use bevy::color::palettes::basic::*;
 use bevy::color::palettes::css::ORANGE;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::text::cosmic_text::ttf_parser::Weight;
 use serde::{Deserialize, Serialize};
@@ -19,11 +20,21 @@ pub struct AsciiAnimation {
     pub frame_num: usize,
     pub frame_size: UVec2,
     pub frame_time: Timer,
+    /// scales the colors of every frame, 1.0 shows them as drawn
+    brightness: f32,
+    redraw: bool,
 }
 impl AsciiAnimation {
     pub fn get_ascii_char_at(&self, pos: &UVec2) -> (char, Color) {
         self.frames[(pos.x as usize + pos.y as usize * self.frame_size.x as usize) + (self.current_frame * self.frame_size.x as usize * self.frame_size.y as usize)]
     }
+    /// the current frame is redrawn right away instead of at the next frame change
+    pub fn set_brightness(&mut self, brightness: f32) {
+        if self.brightness != brightness {
+            self.brightness = brightness;
+            self.redraw = true;
+        }
+    }
 }
 
 #[derive(Default)]
@@ -66,7 +77,91 @@ pub struct AsciiAnimationAsset {
     pub frame_num: usize,
     pub frame_time: f32,
 }
-fn color_from_hex(hex: &str) -> Color {
+/// which side of the playfield a portrait stands on
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortraitSide {
+    Left,
+    Right,
+}
+#[derive(Deserialize, Serialize)]
+pub struct PortraitDef {
+    /// matched against the speaker of dialogue lines
+    pub speaker: String,
+    pub side: PortraitSide,
+    /// animation path of each named clip, `neutral` is worn until a line picks another
+    pub clips: Vec<(String, String)>,
+    #[serde(skip)]
+    pub handles: Vec<(String, Handle<AsciiAnimationAsset>)>,
+}
+impl PortraitDef {
+    /// falls back to the neutral clip for a name the portrait does not have
+    pub fn clip(&self, name: &str) -> Option<&Handle<AsciiAnimationAsset>> {
+        let find = |name: &str| self.handles.iter().find(|(clip, _)| clip == name).map(|(_, handle)| handle);
+        find(name).or_else(|| find(PortraitLibraryAsset::NEUTRAL))
+    }
+}
+/// every character that shows a portrait in dialogue
+#[derive(Asset, Deserialize, Serialize, TypePath)]
+pub struct PortraitLibraryAsset {
+    pub portraits: Vec<PortraitDef>,
+}
+impl PortraitLibraryAsset {
+    pub const NEUTRAL: &'static str = "neutral";
+
+    pub fn portrait(&self, speaker: &str) -> Option<&PortraitDef> {
+        self.portraits.iter().find(|portrait| portrait.speaker == speaker)
+    }
+}
+#[derive(Default)]
+pub struct PortraitLibraryLoader;
+impl AssetLoader for PortraitLibraryLoader {
+    type Asset = PortraitLibraryAsset;
+    type Settings = ();
+    type Error = CustomAssetLoaderError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut library = ron::de::from_bytes::<PortraitLibraryAsset>(&bytes)?;
+        for portrait in library.portraits.iter_mut() {
+            portrait.handles = portrait.clips
+                .iter()
+                .map(|(clip, path)| (clip.clone(), load_context.load(path.clone())))
+                .collect();
+        }
+        Ok(library)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["portrait.ron"]
+    }
+}
+#[derive(Resource)]
+pub struct PortraitLibrary(pub Handle<PortraitLibraryAsset>);
+
+/// the loaded portrait library together with the clips it points at
+#[derive(SystemParam)]
+pub struct Portraits<'w> {
+    library: Res<'w, PortraitLibrary>,
+    libraries: Res<'w, Assets<PortraitLibraryAsset>>,
+    animations: Res<'w, Assets<AsciiAnimationAsset>>,
+}
+impl Portraits<'_> {
+    pub fn library(&self) -> Option<&PortraitLibraryAsset> {
+        self.libraries.get(&self.library.0)
+    }
+
+    /// none until the clip has loaded
+    pub fn animation(&self, portrait: &PortraitDef, clip: &str) -> Option<&AsciiAnimationAsset> {
+        portrait.clip(clip).and_then(|handle| self.animations.get(handle))
+    }
+}
+
+pub fn color_from_hex(hex: &str) -> Color {
     let hex = hex.trim_start_matches('#');
     let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(255);
     let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(255);
@@ -89,6 +184,8 @@ impl AsciiAnimationAsset {
             frame_num: self.frame_num,
             frame_size: self.frame_size,
             frame_time: Timer::from_seconds(self.frame_time, TimerMode::Repeating),
+            brightness: 1.0,
+            redraw: false,
         }
     }
 }
@@ -98,6 +195,8 @@ fn setup_animation(
 ) {
     let main_menu_animation: Handle<AsciiAnimationAsset> = asset_server.load("ascii/animation/test.ron");
     commands.insert_resource(MainMenuAnimation(main_menu_animation));
+    let portraits: Handle<PortraitLibraryAsset> = asset_server.load("ascii/portrait/portraits.portrait.ron");
+    commands.insert_resource(PortraitLibrary(portraits));
 }
 pub fn spawn_ascii_animation(
     commands: &mut Commands,
@@ -157,11 +256,17 @@ fn play_ascii_animation(
 
         if animation.frame_time.just_finished() {
             animation.current_frame = (animation.current_frame + 1) % animation.frame_num;
+            animation.redraw = true;
+        }
+        if animation.redraw {
+            animation.redraw = false;
+            let brightness = animation.brightness;
             for &child in children.iter() {
                 if let Ok((ascii_char, mut text, mut text_color)) = ascii_chars.get_mut(child) {
                     let (ch, color) = animation.get_ascii_char_at(&ascii_char.pos);
+                    let color = color.to_srgba();
                     text.0 = ch.to_string();
-                    text_color.0 = color;
+                    text_color.0 = Color::srgba(color.red * brightness, color.green * brightness, color.blue * brightness, color.alpha);
                 }
             }
         }
@@ -173,6 +278,8 @@ impl Plugin for AsciiAnimationPlugin {
         app
             .init_asset::<AsciiAnimationAsset>()
             .init_asset_loader::<AsciiAnimationLoader>()
+            .init_asset::<PortraitLibraryAsset>()
+            .init_asset_loader::<PortraitLibraryLoader>()
             .add_systems(Startup, setup_animation)
             .add_systems(Update, play_ascii_animation);
     }
@@ -0,0 +1,166 @@
+use bevy::color::palettes::css::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Character {
+    #[default]
+    ReimuHakurei,
+    MarisaKirisame,
+}
+#[derive(Resource, Clone, Copy)]
+pub struct PlayerCharacter(pub Character);
+
+#[derive(Clone, Copy)]
+pub enum ShotKind {
+    Straight,
+    Homing {
+        rotate_speed: f32, // rad/s
+    },
+}
+#[derive(Clone, Copy)]
+pub struct ShotTier {
+    /// the tier is used once the player has more power than this
+    pub min_power: i32,
+    pub num_bullets: i32,
+    pub angle_step_deg: f32,
+    /// horizontal distance between two neighbouring bullets
+    pub spacing: f32,
+}
+#[derive(Clone)]
+pub struct ShotDef {
+    pub glyph: &'static str,
+    pub color: Color,
+    pub speed: f32,
+    pub damage: i32,
+    pub cooldown: f32,
+    pub kind: ShotKind,
+    /// ordered from the highest power requirement to the lowest
+    pub tiers: Vec<ShotTier>,
+}
+impl ShotDef {
+    pub fn tier(&self, power: i32) -> ShotTier {
+        self.tiers
+            .iter()
+            .find(|tier| power > tier.min_power)
+            .or(self.tiers.last())
+            .copied()
+            .unwrap_or(ShotTier {
+                min_power: 0,
+                num_bullets: 1,
+                angle_step_deg: 0.0,
+                spacing: 0.0,
+            })
+    }
+}
+#[derive(Clone)]
+pub struct SupportDef {
+    pub glyph: &'static str,
+    pub color: Color,
+    /// (unfocused, focused) offsets from the player, one pair per unit
+    pub offsets: Vec<(Vec3, Vec3)>,
+    pub spin: f32, // rad/s
+    pub shot: ShotDef,
+}
+#[derive(Resource, Clone)]
+pub struct CharacterDef {
+    pub name: &'static str,
+    pub glyph: &'static str,
+    pub color: Color,
+    pub speed: f32,
+    pub focus_speed: f32,
+    pub hitbox_radius: f32,
+    pub graze_radius: f32,
+    pub shot: ShotDef,
+    pub support: SupportDef,
+}
+impl Character {
+    pub fn def(&self) -> CharacterDef {
+        match self {
+            Character::ReimuHakurei => CharacterDef {
+                name: "Reimu Hakurei",
+                glyph: "@",
+                color: Color::Srgba(RED),
+                speed: 300.0,
+                focus_speed: 150.0,
+                hitbox_radius: 4.0,
+                graze_radius: 18.0,
+                shot: ShotDef {
+                    glyph: "*",
+                    color: Color::Srgba(BLACK),
+                    speed: 700.0,
+                    damage: 1,
+                    cooldown: 0.1,
+                    kind: ShotKind::Homing { rotate_speed: 3.0 },
+                    tiers: vec![
+                        ShotTier { min_power: 50, num_bullets: 7, angle_step_deg: 12.0, spacing: 0.0 },
+                        ShotTier { min_power: 30, num_bullets: 5, angle_step_deg: 12.0, spacing: 0.0 },
+                        ShotTier { min_power: 0, num_bullets: 3, angle_step_deg: 12.0, spacing: 0.0 },
+                    ],
+                },
+                support: SupportDef {
+                    glyph: "O",
+                    color: Color::Srgba(PINK),
+                    offsets: vec![
+                        (Vec3::new(30.0, 0.0, 0.0), Vec3::new(15.0, 30.0, 0.0)),
+                        (Vec3::new(-30.0, 0.0, 0.0), Vec3::new(-15.0, 30.0, 0.0)),
+                    ],
+                    spin: 2.0,
+                    shot: ShotDef {
+                        glyph: "=",
+                        color: Color::Srgba(PURPLE),
+                        speed: 800.0,
+                        damage: 1,
+                        cooldown: 0.2,
+                        kind: ShotKind::Homing { rotate_speed: 1.5 },
+                        tiers: vec![
+                            ShotTier { min_power: 0, num_bullets: 1, angle_step_deg: 0.0, spacing: 0.0 },
+                        ],
+                    },
+                },
+            },
+            Character::MarisaKirisame => CharacterDef {
+                name: "Marisa Kirisame",
+                glyph: "@",
+                color: Color::Srgba(GOLD),
+                speed: 360.0,
+                focus_speed: 160.0,
+                hitbox_radius: 5.0,
+                graze_radius: 15.0,
+                shot: ShotDef {
+                    glyph: "|",
+                    color: Color::Srgba(BLACK),
+                    speed: 1000.0,
+                    damage: 2,
+                    cooldown: 0.08,
+                    kind: ShotKind::Straight,
+                    tiers: vec![
+                        ShotTier { min_power: 50, num_bullets: 4, angle_step_deg: 1.5, spacing: 10.0 },
+                        ShotTier { min_power: 30, num_bullets: 3, angle_step_deg: 1.0, spacing: 10.0 },
+                        ShotTier { min_power: 0, num_bullets: 2, angle_step_deg: 0.0, spacing: 10.0 },
+                    ],
+                },
+                support: SupportDef {
+                    glyph: "*",
+                    color: Color::Srgba(YELLOW),
+                    offsets: vec![
+                        (Vec3::new(25.0, -10.0, 0.0), Vec3::new(8.0, 25.0, 0.0)),
+                        (Vec3::new(-25.0, -10.0, 0.0), Vec3::new(-8.0, 25.0, 0.0)),
+                    ],
+                    spin: 4.0,
+                    shot: ShotDef {
+                        glyph: "!",
+                        color: Color::Srgba(ORANGE),
+                        speed: 1000.0,
+                        damage: 2,
+                        cooldown: 0.12,
+                        kind: ShotKind::Straight,
+                        tiers: vec![
+                            ShotTier { min_power: 0, num_bullets: 1, angle_step_deg: 0.0, spacing: 0.0 },
+                        ],
+                    },
+                },
+            },
+        }
+    }
+}
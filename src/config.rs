@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::save::SaveDir;
+
+const CONFIG_FILE: &str = "config.ron";
+/// window sizes offered in the options menu
+pub const RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+
+/// text language, English is the only one until the game ships translated text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+}
+impl Language {
+    pub const ALL: [Language; 1] = [Language::English];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+        }
+    }
+}
+
+/// player settings from the options menu, missing fields in the file fall back to the defaults
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub bgm_volume: f32,
+    pub sfx_volume: f32,
+    pub fullscreen: bool,
+    pub resolution: (u32, u32),
+    pub vsync: bool,
+    /// lives a practice run starts with
+    pub practice_lives: i32,
+    /// show the hitbox without holding focus
+    pub always_show_hitbox: bool,
+    pub language: Language,
+    pub bloom: bool,
+    /// analog stick deflection below this is ignored
+    pub stick_dead_zone: f32,
+    /// snap the stick to the nearest of 8 directions instead of reading each axis on its own
+    pub stick_snap: bool,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bgm_volume: 0.8,
+            sfx_volume: 0.8,
+            fullscreen: false,
+            resolution: RESOLUTIONS[0],
+            vsync: true,
+            practice_lives: 2,
+            always_show_hitbox: false,
+            language: Language::English,
+            bloom: true,
+            stick_dead_zone: 0.25,
+            stick_snap: true,
+        }
+    }
+}
+impl Config {
+    pub fn load(dir: &SaveDir) -> Self {
+        dir.load(CONFIG_FILE).unwrap_or_default()
+    }
+    pub fn save(&self, dir: &SaveDir) {
+        dir.save(CONFIG_FILE, self);
+    }
+}
+
+pub struct ConfigPlugin;
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        // loaded right away so every startup system already sees the saved settings
+        let save_dir = app.world_mut().get_resource_or_insert_with(SaveDir::default).clone();
+        app.insert_resource(Config::load(&save_dir));
+    }
+}
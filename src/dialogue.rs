@@ -0,0 +1,276 @@
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::asset::io::Reader;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ascii_animation::{CustomAssetLoaderError, PortraitLibraryAsset};
+use crate::input::Action;
+use crate::practice::PracticeRun;
+use crate::replay::ReplayPlayback;
+use crate::state::{AppState, GameState};
+
+#[derive(Default)]
+pub struct DialogueLoader;
+impl AssetLoader for DialogueLoader {
+    type Asset = DialogueAsset;
+    type Settings = ();
+    type Error = CustomAssetLoaderError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let dialogue = ron::de::from_bytes::<DialogueAsset>(&bytes)?;
+        Ok(dialogue)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue.ron"]
+    }
+}
+
+#[derive(Asset, Deserialize, Serialize, TypePath)]
+pub struct DialogueAsset {
+    pub lines: Vec<DialogueLine>,
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DialogueLine {
+    /// name shown above the text, none for narration
+    #[serde(default)]
+    pub speaker: Option<String>,
+    /// clip the speaker's portrait switches to, see `PortraitLibraryAsset`
+    #[serde(default)]
+    pub portrait: Option<String>,
+    /// a line without text moves on as soon as its directions are run
+    #[serde(default)]
+    pub text: String,
+    /// run when the line comes up, and by skipping for every line left
+    #[serde(default)]
+    pub directions: Vec<StageDirection>,
+}
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum StageDirection {
+    /// bring the field back from black
+    FadeIn,
+    /// fade the field to black, the text and portraits stay on top
+    FadeOut,
+    /// music track id, see `MusicLibraryAsset`
+    Music(String),
+    /// (speaker, clip), change a portrait without that character speaking
+    Expression(String, String),
+}
+
+/// a dialogue hook of the stage script was reached
+#[derive(Event)]
+pub struct DialogueCue {
+    pub dialogue: Handle<DialogueAsset>,
+    /// boss the dialogue belongs to, none for the stage intro
+    pub boss: Option<usize>,
+}
+
+/// the dialogue being read, present while in `GameState::Dialogue`
+#[derive(Resource)]
+pub struct ActiveDialogue {
+    pub dialogue: Handle<DialogueAsset>,
+    pub boss: Option<usize>,
+    /// index of the line on screen
+    pub line: usize,
+    /// (speaker, clip) of everyone who appeared so far, in order of appearance
+    pub expressions: Vec<(String, String)>,
+}
+impl ActiveDialogue {
+    pub fn expression(&self, speaker: &str) -> Option<&str> {
+        self.expressions
+            .iter()
+            .find(|(name, _)| name == speaker)
+            .map(|(_, clip)| clip.as_str())
+    }
+    fn set_expression(&mut self, speaker: &str, clip: &str) {
+        match self.expressions.iter_mut().find(|(name, _)| name == speaker) {
+            Some((_, current)) => *current = clip.to_string(),
+            None => self.expressions.push((speaker.to_string(), clip.to_string())),
+        }
+    }
+}
+
+/// black cover over the field, moves toward `target` at a fixed speed
+#[derive(Resource, Default)]
+pub struct ScreenFade {
+    pub alpha: f32,
+    pub target: f32,
+}
+
+/// track picked by a `Music` direction, it replaces the stage and boss bgm until another boss enters
+#[derive(Resource)]
+pub struct DialogueMusic {
+    pub track: String,
+    pub boss: Option<usize>,
+}
+
+fn reset_dialogue(
+    mut commands: Commands,
+) {
+    commands.remove_resource::<ActiveDialogue>();
+    commands.remove_resource::<DialogueMusic>();
+    commands.insert_resource(ScreenFade::default());
+}
+
+fn run_directions(
+    commands: &mut Commands,
+    line: &DialogueLine,
+    active: &mut ActiveDialogue,
+    fade: &mut ScreenFade,
+) {
+    for direction in line.directions.iter() {
+        match direction {
+            StageDirection::FadeIn => fade.target = 0.0,
+            StageDirection::FadeOut => fade.target = 1.0,
+            StageDirection::Music(track) => commands.insert_resource(DialogueMusic {
+                track: track.clone(),
+                boss: active.boss,
+            }),
+            StageDirection::Expression(speaker, clip) => active.set_expression(speaker, clip),
+        }
+    }
+}
+
+/// run the directions from `line` on and stop at the next line with text
+/// returns false once the dialogue has no lines left
+fn show_line(
+    commands: &mut Commands,
+    dialogue: &DialogueAsset,
+    active: &mut ActiveDialogue,
+    line: usize,
+    fade: &mut ScreenFade,
+) -> bool {
+    active.line = line;
+    while let Some(line) = dialogue.lines.get(active.line) {
+        if let Some(speaker) = &line.speaker {
+            // a speaker keeps the clip they last wore unless the line picks one
+            let clip = line.portrait.as_deref()
+                .or(active.expression(speaker))
+                .unwrap_or(PortraitLibraryAsset::NEUTRAL)
+                .to_string();
+            active.set_expression(speaker, &clip);
+        }
+        run_directions(commands, line, active, fade);
+        if !line.text.is_empty() {
+            return true;
+        }
+        active.line += 1;
+    }
+    false
+}
+
+fn end_dialogue(
+    commands: &mut Commands,
+    next_state: &mut NextState<GameState>,
+    fade: &mut ScreenFade,
+) {
+    commands.remove_resource::<ActiveDialogue>();
+    // the fight goes on in plain sight whatever the script left the screen at
+    fade.target = 0.0;
+    next_state.set(GameState::Running);
+}
+
+fn start_dialogue(
+    mut commands: Commands,
+    mut cues: EventReader<DialogueCue>,
+    dialogues: Res<Assets<DialogueAsset>>,
+    mut fade: ResMut<ScreenFade>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(cue) = cues.read().last() else {
+        return;
+    };
+    let Some(dialogue) = dialogues.get(&cue.dialogue) else {
+        warn!("Dialogue {:?} is not loaded", cue.dialogue.path());
+        return;
+    };
+    let mut active = ActiveDialogue {
+        dialogue: cue.dialogue.clone(),
+        boss: cue.boss,
+        line: 0,
+        expressions: Vec::new(),
+    };
+    if show_line(&mut commands, dialogue, &mut active, 0, &mut fade) {
+        commands.insert_resource(active);
+        next_state.set(GameState::Dialogue);
+    }
+}
+
+fn advance_dialogue(
+    mut commands: Commands,
+    mut active: ResMut<ActiveDialogue>,
+    dialogues: Res<Assets<DialogueAsset>>,
+    mut fade: ResMut<ScreenFade>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let next_line = active.line + 1;
+    let shown = dialogues
+        .get(&active.dialogue)
+        .is_some_and(|dialogue| show_line(&mut commands, dialogue, &mut active, next_line, &mut fade));
+    if !shown {
+        end_dialogue(&mut commands, &mut next_state, &mut fade);
+    }
+}
+
+fn skip_dialogue(
+    mut commands: Commands,
+    mut active: ResMut<ActiveDialogue>,
+    dialogues: Res<Assets<DialogueAsset>>,
+    mut fade: ResMut<ScreenFade>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // the lines go unread but the music and fades still happen
+    if let Some(dialogue) = dialogues.get(&active.dialogue) {
+        for line in dialogue.lines.iter().skip(active.line + 1) {
+            run_directions(&mut commands, line, &mut active, &mut fade);
+        }
+    }
+    end_dialogue(&mut commands, &mut next_state, &mut fade);
+}
+
+fn fade_screen(
+    time: Res<Time<Real>>,
+    mut fade: ResMut<ScreenFade>,
+) {
+    const FADE_SECS: f32 = 0.8;
+
+    if fade.alpha == fade.target {
+        return;
+    }
+    let step = time.delta_secs() / FADE_SECS;
+    fade.alpha = if fade.alpha < fade.target {
+        (fade.alpha + step).min(fade.target)
+    } else {
+        (fade.alpha - step).max(fade.target)
+    };
+}
+
+pub struct DialoguePlugin;
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<DialogueAsset>()
+            .init_asset_loader::<DialogueLoader>()
+            .init_resource::<ScreenFade>()
+            .add_event::<DialogueCue>()
+            .add_systems(OnEnter(AppState::InGame), reset_dialogue)
+            // practice and replays go straight to the shooting
+            .add_systems(Update, start_dialogue.run_if(
+                in_state(GameState::Running)
+                    .and(on_event::<DialogueCue>)
+                    .and(not(resource_exists::<PracticeRun>))
+                    .and(not(resource_exists::<ReplayPlayback>))
+            ))
+            .add_systems(Update, (
+                skip_dialogue.run_if(input_just_pressed(Action::Skip)),
+                advance_dialogue.run_if(input_just_pressed(Action::Shoot).and(not(input_just_pressed(Action::Skip)))),
+            ).run_if(in_state(GameState::Dialogue).and(resource_exists::<ActiveDialogue>)))
+            .add_systems(Update, fade_screen.run_if(in_state(AppState::InGame)));
+    }
+}
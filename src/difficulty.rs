@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::stage::{BossDef, BossPhaseDef, BulletDef, BulletKindDef, EmitterDef, WaveDef};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Lunatic,
+    /// only used by the extra stage
+    Extra,
+}
+#[derive(Resource, Clone, Copy)]
+pub struct PlayerDifficulty(pub Difficulty);
+
+/// multipliers applied on top of the stage scripts, which are written for Normal
+#[derive(Resource, Clone, Copy)]
+pub struct DifficultyDef {
+    pub bullet_count: f32,
+    pub bullet_speed: f32,
+    pub fire_rate: f32,
+    pub enemy_health: f32,
+    pub lives: i32,
+    pub bombs: i32,
+}
+impl Difficulty {
+    pub fn def(&self) -> DifficultyDef {
+        match self {
+            Difficulty::Easy => DifficultyDef {
+                bullet_count: 0.6,
+                bullet_speed: 0.8,
+                fire_rate: 0.7,
+                enemy_health: 0.8,
+                lives: 4,
+                bombs: 3,
+            },
+            Difficulty::Normal => DifficultyDef {
+                bullet_count: 1.0,
+                bullet_speed: 1.0,
+                fire_rate: 1.0,
+                enemy_health: 1.0,
+                lives: 2,
+                bombs: 3,
+            },
+            Difficulty::Hard => DifficultyDef {
+                bullet_count: 1.3,
+                bullet_speed: 1.15,
+                fire_rate: 1.25,
+                enemy_health: 1.2,
+                lives: 2,
+                bombs: 3,
+            },
+            Difficulty::Lunatic => DifficultyDef {
+                bullet_count: 1.6,
+                bullet_speed: 1.3,
+                fire_rate: 1.5,
+                enemy_health: 1.4,
+                lives: 2,
+                bombs: 2,
+            },
+            // the extra stage script is written for this difficulty, so nothing is scaled
+            Difficulty::Extra => DifficultyDef {
+                bullet_count: 1.0,
+                bullet_speed: 1.0,
+                fire_rate: 1.0,
+                enemy_health: 1.0,
+                lives: 2,
+                bombs: 3,
+            },
+        }
+    }
+}
+impl DifficultyDef {
+    pub fn scale_health(&self, health: i32) -> i32 {
+        ((health as f32 * self.enemy_health).round() as i32).max(1)
+    }
+    pub fn scale_bullet(&self, bullet: &BulletDef) -> BulletDef {
+        let kind = match bullet.kind {
+            BulletKindDef::Homing { speed, rotate_speed } => BulletKindDef::Homing {
+                speed: speed * self.bullet_speed,
+                rotate_speed,
+            },
+            BulletKindDef::Spiral { radius, radius_growth, angular_speed, angle, forward_speed } => BulletKindDef::Spiral {
+                radius,
+                radius_growth,
+                angular_speed,
+                angle,
+                forward_speed: forward_speed * self.bullet_speed,
+            },
+            ref kind => kind.clone(),
+        };
+        BulletDef { kind, ..bullet.clone() }
+    }
+    pub fn scale_emitter(&self, emitter: &EmitterDef) -> EmitterDef {
+        match emitter {
+            EmitterDef::None => EmitterDef::None,
+            EmitterDef::Single { bullet, aim, speed, cooldown, times } => EmitterDef::Single {
+                bullet: self.scale_bullet(bullet),
+                aim: aim.clone(),
+                speed: speed * self.bullet_speed,
+                cooldown: cooldown / self.fire_rate,
+                times: *times,
+            },
+            EmitterDef::Fan { bullet, aim, speed, num_bullets, angle_deg, cooldown, times } => {
+                let scaled_num = ((*num_bullets as f32 * self.bullet_count).round() as i32).max(1);
+                // keep the fan covering the same arc
+                let scaled_angle = angle_deg * *num_bullets as f32 / scaled_num as f32;
+                EmitterDef::Fan {
+                    bullet: self.scale_bullet(bullet),
+                    aim: aim.clone(),
+                    speed: speed * self.bullet_speed,
+                    num_bullets: scaled_num,
+                    angle_deg: scaled_angle,
+                    cooldown: cooldown / self.fire_rate,
+                    times: *times,
+                }
+            }
+        }
+    }
+    pub fn scale_wave(&self, wave: &WaveDef) -> WaveDef {
+        WaveDef {
+            health: self.scale_health(wave.health),
+            emitter: self.scale_emitter(&wave.emitter),
+            ..wave.clone()
+        }
+    }
+    pub fn scale_boss(&self, boss: &BossDef) -> BossDef {
+        BossDef {
+            phases: boss.phases
+                .iter()
+                .map(|phase| BossPhaseDef {
+                    health: self.scale_health(phase.health),
+                    emitters: phase.emitters.iter().map(|emitter| self.scale_emitter(emitter)).collect(),
+                    ..phase.clone()
+                })
+                .collect(),
+            ..boss.clone()
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use crate::resource::SpellCard;
+
+/// one volley of the player's shot
+#[derive(Event)]
+pub struct PlayerShot {
+    pub position: Vec2,
+}
+/// a player shot landed on an enemy, `position` is where the bullet hit
+#[derive(Event)]
+pub struct EnemyHit {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub damage: i32,
+}
+/// a regular enemy was destroyed by a shot or a bomb, bosses send `BossDefeated` instead
+#[derive(Event)]
+pub struct EnemyKilled {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub score: u64,
+}
+#[derive(Event)]
+pub struct PlayerHit {
+    pub entity: Entity,
+    pub position: Vec2,
+}
+#[derive(Event)]
+pub struct Grazed {
+    pub bullet: Entity,
+    pub position: Vec2,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Power,
+    Point,
+}
+#[derive(Event)]
+pub struct ItemCollected {
+    pub item: Entity,
+    pub kind: ItemKind,
+    pub position: Vec2,
+    /// score awarded for the pickup
+    pub value: u64,
+}
+#[derive(Event)]
+pub struct BombUsed {
+    pub spell_card: SpellCard,
+    pub position: Vec2,
+}
+#[derive(Event)]
+pub struct LaserTelegraphStarted {
+    pub entity: Entity,
+    pub position: Vec2,
+}
+/// the telegraph is over and the laser starts colliding
+#[derive(Event)]
+pub struct LaserFired {
+    pub entity: Entity,
+    pub position: Vec2,
+}
+/// a boss phase with a spell card began, counts as an attempt at it
+#[derive(Event)]
+pub struct SpellCardDeclared {
+    pub name: String,
+}
+/// a spell card was beaten without losing a life or bombing
+#[derive(Event)]
+pub struct SpellCardCaptured {
+    pub name: String,
+}
+
+pub struct GameEventPlugin;
+impl Plugin for GameEventPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<PlayerShot>()
+            .add_event::<EnemyHit>()
+            .add_event::<EnemyKilled>()
+            .add_event::<PlayerHit>()
+            .add_event::<Grazed>()
+            .add_event::<ItemCollected>()
+            .add_event::<BombUsed>()
+            .add_event::<LaserTelegraphStarted>()
+            .add_event::<LaserFired>()
+            .add_event::<SpellCardDeclared>()
+            .add_event::<SpellCardCaptured>();
+    }
+}
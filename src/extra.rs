@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::character::{Character, PlayerCharacter};
+use crate::game::StageRunner;
+use crate::practice::PracticeRun;
+use crate::replay::ReplayPlayback;
+use crate::save::SaveDir;
+use crate::stage::{StageFinished, StageList};
+
+const CLEAR_FILE: &str = "clear.ron";
+
+/// characters that cleared the main stages, each one unlocks the extra stage for itself
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct ClearRecords {
+    pub cleared: Vec<Character>,
+}
+impl ClearRecords {
+    pub fn is_cleared(&self, character: Character) -> bool {
+        self.cleared.contains(&character)
+    }
+    pub fn any_cleared(&self) -> bool {
+        !self.cleared.is_empty()
+    }
+    pub fn load(dir: &SaveDir) -> Self {
+        dir.load(CLEAR_FILE).unwrap_or_default()
+    }
+    pub fn save(&self, dir: &SaveDir) {
+        dir.save(CLEAR_FILE, self);
+    }
+}
+
+fn load_clear_records(
+    mut commands: Commands,
+    save_dir: Res<SaveDir>,
+) {
+    commands.insert_resource(ClearRecords::load(&save_dir));
+}
+
+fn record_clear(
+    mut stage_finished: EventReader<StageFinished>,
+    runner: Res<StageRunner>,
+    stage_list: Res<StageList>,
+    character: Res<PlayerCharacter>,
+    mut records: ResMut<ClearRecords>,
+    save_dir: Res<SaveDir>,
+) {
+    let last_stage = stage_list.stages.len().saturating_sub(1);
+    let cleared = stage_finished.read().any(|event| event.stage >= last_stage);
+    if !cleared || runner.extra || records.is_cleared(character.0) {
+        return;
+    }
+    info!("{} cleared the game, the extra stage is unlocked", character.0.def().name);
+    records.cleared.push(character.0);
+    records.save(&save_dir);
+}
+
+pub struct ExtraPlugin;
+impl Plugin for ExtraPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, load_clear_records)
+            // only a regular run counts as a clear
+            .add_systems(FixedUpdate, record_clear.run_if(
+                on_event::<StageFinished>
+                    .and(not(resource_exists::<PracticeRun>))
+                    .and(not(resource_exists::<ReplayPlayback>))
+            ));
+    }
+}
@@ -0,0 +1,2839 @@
+use crate::save::{today, SavePlugin};
+use crate::rng::{CosmeticRng, GameRng, RngPlugin};
+use crate::input::{Action, player_input_just_pressed, player_input_just_released, player_input_pressed, InputFrame, PlayerInput, PlayerInputPlugin};
+use crate::replay::{ReplayPlayback, ReplayPlugin};
+use crate::tick::{TickRate, TickRatePlugin};
+use crate::config::{Config, ConfigPlugin};
+use crate::event::{BombUsed, EnemyHit, EnemyKilled, GameEventPlugin, Grazed, ItemCollected, ItemKind, LaserFired, LaserTelegraphStarted, PlayerHit, PlayerShot, SpellCardCaptured, SpellCardDeclared};
+use crate::practice::{PracticePlugin, PracticeRun, PracticeTarget};
+use crate::extra::ExtraPlugin;
+use crate::dialogue::{DialogueCue, DialoguePlugin};
+use rand::Rng;
+use crate::high_score::{HighScoreEntry, HighScorePlugin, HighScores, PendingHighScore};
+use crate::state::GameState;
+use crate::resource::AsciiFont;
+use crate::ui::{PlayerGrazeText, PlayerPointsText};
+use crate::ui::{PlayerBombsText, PlayerPowersText};
+use crate::ui::{DebugOverlayText, HiScoreText, PlayerLivesText, ScoreText};
+use crate::ui::{BossHealthBarText, BossTimerText, GameContinued};
+use crate::resource::WindowSize;
+use crate::resource::{PlayerSpellCard, SpellCard};
+use crate::difficulty::{Difficulty, DifficultyDef, PlayerDifficulty};
+use crate::character::{Character, CharacterDef, PlayerCharacter, ShotDef, ShotKind};
+use crate::state::AppState;
+use crate::ascii_animation::color_from_hex;
+use crate::stage::{AimDef, BossDef, BossDefeated, BossPhaseDef, BulletDef, BulletKindDef, EmitterDef, MovementDef, ScheduledSpawn, StageAsset, StageFinished, StageList, StagePlugin, WaveDef};
+use bevy::color::palettes::css::*;
+use bevy::color::palettes::tailwind::*;
+use bevy::ecs::query::QueryData;
+use bevy::input::common_conditions::*;
+use bevy::text::{JustifyText, Text2d, TextFont, TextLayout};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+const PLAYER_RESPAWN_POS: Vec3 = Vec3::new(-200.0, -250.0, 0.0);
+#[derive(Component, Clone)]
+enum BulletTarget {
+    Player,
+    Enemy,
+}
+impl BulletTarget {
+    pub fn collision_groups(&self) -> CollisionGroups {
+        match self {
+            BulletTarget::Player => CollisionGroups::new(Group::GROUP_8, Group::GROUP_1 | Group::GROUP_7),
+            BulletTarget::Enemy => CollisionGroups::new(Group::GROUP_2, Group::GROUP_4),
+        }
+    }
+}
+
+#[derive(Component)]
+struct EnemyDeathParticle(Timer);
+#[derive(Component)]
+struct PlayerDeathParticle(Timer);
+#[derive(Component)]
+struct EnemyHitParticle(Timer);
+#[derive(Component, Clone)]
+struct HomingBullet {
+    speed: f32,
+    rotate_speed: f32, // rad/s
+}
+#[derive(Component, Clone)]
+struct SpiralBullet {
+    radius: f32,
+    radius_growth: f32,
+    angular_speed: f32, // rad/s
+    angle: f32, // current angle in rad
+    forward_velocity: Vec2,
+}
+#[derive(Component)]
+struct GrazingBullet{
+    speed_decay: f32,
+    original_color: Color,
+}
+#[derive(Component, Clone)]
+struct LaserBullet {
+    telegraph_duration: Timer,
+    duration: Timer,
+    animation_timer: Timer,
+    width: usize, // columns
+}
+#[derive(Clone)]
+enum BulletType {
+    Normal,
+    Homing(HomingBullet),
+    Spiral(SpiralBullet),
+    Laser(LaserBullet),
+}
+impl BulletType {
+    pub fn insert_into(&self, entity: &mut EntityCommands) {
+        match self {
+            BulletType::Normal => {}
+            BulletType::Homing(homing) => { entity.insert(homing.clone()); },
+            BulletType::Spiral(spiral) => { entity.insert(spiral.clone()); },
+            BulletType::Laser(laser) => { entity.insert(laser.clone()); },
+        }
+    }
+}
+#[derive(Bundle)]
+struct BulletBundle {
+    target: BulletTarget,
+    text: Text2d,
+    text_font: TextFont,
+    text_layout: TextLayout,
+    text_color: TextColor,
+    collider: Collider,
+    rigid_body: RigidBody,
+    active_events: ActiveEvents,
+    collision_groups: CollisionGroups,
+}
+
+#[derive(Clone)]
+struct BulletInfo {
+    bullet_type: BulletType,
+    target: BulletTarget,
+    text: Text2d,
+    text_font: TextFont,
+    text_layout: TextLayout,
+    text_color: TextColor,
+    collider: Collider,
+}
+impl BulletInfo {
+    pub fn to_bundle(&self) -> BulletBundle {
+        BulletBundle {
+            target: self.target.clone(),
+            text: self.text.clone(),
+            text_font: self.text_font.clone(),
+            text_layout: self.text_layout.clone(),
+            text_color: self.text_color.clone(),
+            collider: self.collider.clone(),
+            rigid_body: RigidBody::KinematicVelocityBased,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            collision_groups: self.target.collision_groups(),
+        }
+    }
+}
+#[derive(Component)]
+pub struct JudgePoint;
+#[derive(Component)]
+pub struct Player;
+#[derive(Component)]
+pub struct GrazeZone;
+#[derive(Component)]
+struct ShootCooldown(Timer);
+#[derive(Component)]
+pub struct Enemy;
+#[derive(Component)]
+pub struct Health(pub i32);
+#[derive(Component)]
+pub struct Boss {
+    name: String,
+    phases: Vec<BossPhaseDef>,
+    phase: usize,
+    timer: Timer,
+    home: Vec2,
+    score: u64,
+    /// the current phase has been declared
+    phase_started: bool,
+    /// a death or a bomb during the current phase, a failed spell card is not captured
+    failed: bool,
+}
+#[derive(Component)]
+struct BossEmitter;
+#[derive(Component)]
+struct LinearMovement(Vec2);
+#[derive(Component)]
+struct SingleShoot {
+    bullet: BulletInfo,
+    velocity: Vec2,
+    cooldown: Timer,
+    times: i32,
+}
+#[derive(Component)]
+struct FanShoot {
+    bullet: BulletInfo,
+    num_bullets: i32,
+    angle_deg: f32,
+    velocity: Vec2,
+    cooldown: Timer,
+    times: i32,
+}
+#[derive(Component)]
+pub struct PowerItem;
+#[derive(Component)]
+pub struct PointItem;
+#[derive(Component)]
+pub struct Invincible(pub Timer);
+#[derive(Component)]
+struct BulletDamage(i32);
+#[derive(Component)]
+struct Piercing;
+#[derive(Component)]
+struct FollowPlayer(Vec3);
+#[derive(Clone, Copy)]
+enum BombArea {
+    Circle(f32), // radius around the player
+    Column(f32), // half width, above the player
+}
+#[derive(Component)]
+struct ActiveBomb {
+    duration: Timer,
+    damage_tick: Timer,
+    area: BombArea,
+    damage: i32,
+}
+#[derive(Component)]
+struct BombParticle(Timer);
+#[derive(Component)]
+struct AutoCollect;
+#[derive(Component)]
+struct SupportUnit {
+    original_position: Vec3,
+    focus_position: Vec3,
+}
+#[derive(Resource)]
+pub struct ShowColliderDebug(pub bool);
+#[derive(Resource)]
+pub struct StageRunner {
+    pub stage: usize,
+    pub elapsed: f32,
+    schedule: Option<Vec<ScheduledSpawn>>,
+    cursor: usize,
+    next_boss: usize,
+    pub boss_active: bool,
+    pub finished: bool,
+    /// playing the extra stage instead of the main ones
+    pub extra: bool,
+    /// (boss, phase) fought alone by spell practice
+    spell_practice: Option<(usize, usize)>,
+}
+impl StageRunner {
+    pub fn new(stage: usize) -> Self {
+        Self {
+            stage,
+            elapsed: 0.0,
+            schedule: None,
+            cursor: 0,
+            next_boss: 0,
+            boss_active: false,
+            finished: false,
+            extra: false,
+            spell_practice: None,
+        }
+    }
+    /// skip the waves and fight a single phase of one boss
+    pub fn spell_practice(stage: usize, boss: usize, phase: usize) -> Self {
+        Self {
+            spell_practice: Some((boss, phase)),
+            ..Self::new(stage)
+        }
+    }
+    /// index of the last boss that entered the stage
+    pub fn current_boss(&self) -> Option<usize> {
+        self.next_boss.checked_sub(1)
+    }
+}
+#[derive(Resource)]
+pub struct PlayerLives(pub i32);
+#[derive(Resource)]
+pub struct PlayerBombs(pub i32);
+#[derive(Resource)]
+pub struct PlayerPowers(pub i32);
+#[derive(Resource)]
+pub struct PlayerPoints(pub i32);
+#[derive(Resource)]
+pub struct PlayerGraze(pub i32);
+#[derive(Resource)]
+pub struct PlayerContinues(pub i32);
+#[derive(Resource)]
+pub struct Score(pub u64);
+#[derive(Resource)]
+pub struct HiScore(pub u64);
+#[derive(Component)]
+struct ScoreValue(u64);
+#[derive(Resource)]
+struct SlowdownTracker {
+    tick_rate: f32,
+    ticks: u32,
+    elapsed: f32,
+}
+impl SlowdownTracker {
+    fn new(tick_rate: &TickRate) -> Self {
+        SlowdownTracker {
+            tick_rate: tick_rate.0 as f32,
+            ticks: 0,
+            elapsed: 0.0,
+        }
+    }
+    /// percentage of ticks lost compared to real time
+    fn rate(&self) -> f32 {
+        if self.elapsed <= 0.0 {
+            return 0.0;
+        }
+        ((1.0 - self.ticks as f32 / (self.elapsed * self.tick_rate)) * 100.0).clamp(0.0, 100.0)
+    }
+}
+fn attract_items(
+    rapier_context: ReadDefaultRapierContext,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    mut item_query: Query<(&mut Velocity, &Transform, Has<AutoCollect>), Or<(With<PowerItem>, With<PointItem>)>>,
+    window: Res<WindowSize>,
+) {
+    const TOP_ZONE_HEIGHT: f32 = 150.0;
+    const AUTO_ATTRACT_SPEED: f32 = 400.0;
+    const ATTRACT_RADIUS: f32 = 80.0;
+    const ATTRACT_SPEED: f32 = 100.0;
+
+    if let Ok((player_entity, player_transform)) = player_query.get_single() {
+        let player_pos = player_transform.translation.truncate();
+        let shape = Collider::ball(ATTRACT_RADIUS);
+
+        if player_pos.y > window.height / 2.0 - TOP_ZONE_HEIGHT {
+            let player_pos = player_transform.translation.truncate();
+
+            for (mut item_velocity, item_transform, _) in item_query.iter_mut() {
+                let dir = (player_pos - item_transform.translation.truncate()).normalize_or_zero();
+                item_velocity.linvel = dir * AUTO_ATTRACT_SPEED;
+            }
+
+            return;
+        } else {
+            for (mut item_velocity, item_transform, auto_collect) in item_query.iter_mut() {
+                if auto_collect {
+                    let dir = (player_pos - item_transform.translation.truncate()).normalize_or_zero();
+                    item_velocity.linvel = dir * AUTO_ATTRACT_SPEED;
+                }
+            }
+
+            rapier_context.intersections_with_shape(
+                player_pos,
+                0.0,
+                &shape,
+                QueryFilter {
+                    exclude_rigid_body: Some(player_entity),
+                    groups: Some(CollisionGroups::new(Group::ALL, Group::GROUP_6)),
+                    ..default()
+                },
+                |item_entity| {
+                    if let Ok((mut velocity, item_pos, auto_collect)) = item_query.get_mut(item_entity) {
+                        if auto_collect {
+                            return true;
+                        }
+                        let dir = (player_pos - item_pos.translation.truncate()).normalize_or_zero();
+                        let distance = player_pos.distance(item_pos.translation.truncate());
+                        let strength = 1.0 - (distance / ATTRACT_RADIUS);
+                        let attract_speed = ATTRACT_SPEED * (1.0 + strength.clamp(0.0, 1.0));
+                        velocity.linvel = dir * attract_speed;
+                    }
+                    true
+                }
+            );
+        }
+    }
+}
+
+fn update_lives_text(
+    lives: Res<PlayerLives>,
+    mut query: Query<&mut Text2d, With<PlayerLivesText>>,
+) {
+    let num = "@".repeat(lives.0.max(0) as usize);
+    let margins = " ".repeat(lives.0.max(0) as usize);
+    for mut text in query.iter_mut() {
+        text.0 = format!("  {}Player: {}", margins, num);
+    }
+}
+fn update_bombs_text(
+    bombs: Res<PlayerBombs>,
+    mut query: Query<&mut Text2d, With<PlayerBombsText>>,
+) {
+    let num = "$".repeat(bombs.0.max(0) as usize);
+    let margins = " ".repeat(bombs.0.max(0) as usize);
+    for mut text in query.iter_mut() {
+        text.0 = format!("{}Bomb: {}", margins, num);
+    }
+}
+fn update_powers_text(
+    powers: Res<PlayerPowers>,
+    mut query: Query<&mut Text2d, With<PlayerPowersText>>,
+) {
+    let num = powers.0.to_string();
+    let margins = " ".repeat(powers.0.to_string().len().max(0));
+    for mut text in query.iter_mut() {
+        text.0 = format!(" {}Power: {}", margins, num);
+    }
+}
+fn update_graze_text(
+    graze: Res<PlayerGraze>,
+    mut query: Query<&mut Text2d, With<PlayerGrazeText>>,
+) {
+    let num = graze.0.to_string();
+    let margins = " ".repeat(graze.0.to_string().len().max(0));
+    for mut text in query.iter_mut() {
+        text.0 = format!(" {}Graze: {}", margins, num);
+    }
+}
+fn update_score_text(
+    score: Res<Score>,
+    mut hi_score: ResMut<HiScore>,
+    mut score_query: Query<&mut Text2d, (With<ScoreText>, Without<HiScoreText>)>,
+    mut hi_score_query: Query<&mut Text2d, (With<HiScoreText>, Without<ScoreText>)>,
+) {
+    if score.0 > hi_score.0 {
+        hi_score.0 = score.0;
+    }
+    let num = score.0.to_string();
+    let margins = " ".repeat(num.len());
+    for mut text in score_query.iter_mut() {
+        text.0 = format!("   {}Score: {}", margins, num);
+    }
+    let num = hi_score.0.to_string();
+    let margins = " ".repeat(num.len());
+    for mut text in hi_score_query.iter_mut() {
+        text.0 = format!("{}Hi-Score: {}", margins, num);
+    }
+}
+fn score_graze(
+    mut events: EventReader<Grazed>,
+    mut graze: ResMut<PlayerGraze>,
+    mut score: ResMut<Score>,
+) {
+    const GRAZE_SCORE: u64 = 500;
+    for _ in events.read() {
+        graze.0 += 1;
+        score.0 += GRAZE_SCORE;
+    }
+}
+fn update_points_text(
+    points: Res<PlayerPoints>,
+    mut query: Query<&mut Text2d, With<PlayerPointsText>>,
+) {
+    let num = points.0.to_string();
+    let margins = " ".repeat(points.0.to_string().len().max(0));
+    for mut text in query.iter_mut() {
+        text.0 = format!(" {}Point: {}", margins, num);
+    }
+}
+
+fn linear_movement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &LinearMovement, &mut Velocity)>,
+) {
+    for (entity, movement, mut velocity) in query.iter_mut() {
+        velocity.linvel += movement.0;
+        commands.entity(entity).remove::<LinearMovement>();
+    }
+}
+
+fn laser_bullet(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut LaserBullet,
+        &mut TextColor,
+        &mut CollisionGroups,
+        &mut Text2d,
+        &BulletTarget,
+        &Transform,
+    )>,
+    mut telegraph_started: EventWriter<LaserTelegraphStarted>,
+    mut laser_fired: EventWriter<LaserFired>,
+) {
+    for (
+        laser_entity,
+        mut laser,
+        mut text_color,
+        mut groups,
+        mut text,
+        target,
+        transform,
+    ) in query.iter_mut() {
+        if !laser.telegraph_duration.finished() {
+            // telegraph phase
+            if laser.telegraph_duration.elapsed().is_zero() {
+                telegraph_started.send(LaserTelegraphStarted {
+                    entity: laser_entity,
+                    position: transform.translation.truncate(),
+                });
+            }
+            laser.telegraph_duration.tick(time.delta());
+
+            let progress = laser.telegraph_duration.elapsed_secs()
+                / laser.telegraph_duration.duration().as_secs_f32();
+            let eased_alpha = progress.clamp(0.0, 1.0).powf(5.0);
+
+            let mut color = text_color.0;
+            color.set_alpha(eased_alpha);
+            text_color.0 = color;
+
+            if laser.telegraph_duration.finished() {
+                *groups = target.collision_groups();
+                laser_fired.send(LaserFired {
+                    entity: laser_entity,
+                    position: transform.translation.truncate(),
+                });
+            }
+        } else {
+            if laser.duration.finished() {
+                commands.entity(laser_entity).despawn();
+            } else {
+                laser.duration.tick(time.delta());
+
+                if !laser.animation_timer.finished() {
+                    laser.animation_timer.tick(time.delta());
+                }
+                let total_rows = text.0.lines().count();
+                let animation_progress = laser.animation_timer.elapsed_secs() / laser.animation_timer.duration().as_secs_f32();
+                let duration_progress = laser.duration.elapsed_secs() / laser.duration.duration().as_secs_f32();
+
+                let rows_to_replace = (total_rows as f32 * animation_progress.clamp(0.0, 1.0)).ceil() as usize;
+                let rows_decays = (total_rows as f32 * (duration_progress - 0.85).max(0.0) / 0.15).ceil() as usize;
+                text.0 = format!("{}{}{}",
+                    format!("{}\n", " ".repeat(laser.width)).repeat(rows_decays),
+                    format!("{}\n", "V".repeat(laser.width)).repeat(rows_to_replace - rows_decays),
+                    format!("{}\n", "!".repeat(laser.width)).repeat(total_rows - rows_to_replace));
+            }
+        }
+    }
+}
+
+fn homing_bullet_find_nearest<'a>(
+    reference: Vec3,
+    targets: impl Iterator<Item = &'a Transform>,
+) -> Option<&'a Transform> {
+    targets.min_by(|a, b| {
+        let da = reference.distance_squared(a.translation);
+        let db = reference.distance_squared(b.translation);
+        da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+    })
+}
+
+
+fn homing_bullet(
+    mut query: Query<(
+        &mut Velocity,
+        &Transform,
+        &HomingBullet,
+        &BulletTarget,
+        Option<&GrazingBullet>,
+    )>,
+    players: Query<&Transform, With<Player>>,
+    enemies: Query<&Transform, With<Enemy>>,
+    time: Res<Time>,
+) {
+    for (
+        mut velocity,
+        bullet_transform,
+        homing,
+        target,
+        option_graze
+    ) in query.iter_mut() {
+        let current_dir = velocity.linvel.normalize_or_zero();
+
+        let target_transform = match target {
+            BulletTarget::Player => homing_bullet_find_nearest(bullet_transform.translation, players.iter()),
+            BulletTarget::Enemy => homing_bullet_find_nearest(bullet_transform.translation, enemies.iter()),
+        };
+
+        if let Some(target) = target_transform {
+            let desired_dir = (target.translation.truncate() - bullet_transform.translation.truncate()).normalize_or_zero();
+            let angle_between = current_dir.angle_to(desired_dir);
+            let max_rotate = homing.rotate_speed * time.delta_secs();
+            let clamped_angle = angle_between.clamp(-max_rotate, max_rotate);
+            let new_dir = current_dir.rotate(Vec2::from_angle(clamped_angle)).normalize_or_zero();
+
+            let decay = if let Some(graze) = option_graze {
+                graze.speed_decay
+            } else {
+                1.0
+            };
+            velocity.linvel = new_dir * homing.speed * decay;
+        }
+    }
+}
+
+fn spiral_bullet(
+    mut query: Query<(&mut SpiralBullet, &mut Velocity, Option<&GrazingBullet>,)>,
+    time: Res<Time>,
+) {
+    for (mut spiral, mut velocity, option_graze) in query.iter_mut() {
+        let tangent = Vec2::from_angle(spiral.angle).perp().normalize_or_zero();
+
+        let decay = if let Some(graze) = option_graze {
+            graze.speed_decay
+        } else {
+            1.0
+        };
+
+        velocity.linvel = tangent * spiral.radius * spiral.angular_speed + spiral.forward_velocity;
+        velocity.linvel *= decay;
+
+        spiral.angle += spiral.angular_speed * time.delta_secs();
+        spiral.radius += spiral.radius_growth * time.delta_secs();
+    }
+}
+
+fn single_shoot(
+    mut commands: Commands,
+    mut query: Query<(Entity, &GlobalTransform, &mut SingleShoot, Option<&BulletDamage>)>,
+    time: Res<Time>,
+) {
+    for (entity, transform, mut shoot, damage) in query.iter_mut() {
+        shoot.cooldown.tick(time.delta());
+
+        if shoot.cooldown.finished() {
+            if shoot.times > 0 {
+                shoot.times -= 1;
+            } else if shoot.times == 0 {
+                commands.entity(entity).remove::<SingleShoot>();
+                continue
+            }
+
+            let spawn_pos = transform.translation();
+
+            let mut bullet_entity = commands.spawn((
+                StateScoped(AppState::InGame),
+                shoot.bullet.to_bundle(),
+                Transform::from_translation(spawn_pos),
+                Velocity::linear(shoot.velocity),
+            ));
+            if let Some(damage) = damage {
+                bullet_entity.insert(BulletDamage(damage.0));
+            }
+
+            match shoot.bullet.clone().bullet_type {
+                BulletType::Normal => {
+                    shoot.bullet.bullet_type.insert_into(&mut bullet_entity);
+                }
+                BulletType::Homing(_) => {
+                    shoot.bullet.bullet_type.insert_into(&mut bullet_entity);
+                }
+                BulletType::Spiral(mut spiral) => {
+                    spiral.forward_velocity = shoot.velocity;
+                    BulletType::Spiral(spiral).insert_into(&mut bullet_entity);
+                }
+                BulletType::Laser(_) => {
+                    let rotation = Quat::from_rotation_z(shoot.velocity.normalize_or_zero().to_angle());
+                    bullet_entity.insert(Transform {
+                        translation: spawn_pos,
+                        rotation,
+                        ..default()
+                    });
+                    bullet_entity.insert(CollisionGroups::new(Group::NONE, Group::NONE));
+                    bullet_entity.insert(Velocity::zero());
+                    shoot.bullet.bullet_type.insert_into(&mut bullet_entity);
+                }
+            }
+
+            shoot.cooldown.reset();
+        }
+    }
+}
+
+fn fan_shoot(
+    mut commands: Commands,
+    mut query: Query<(Entity, &GlobalTransform, &mut FanShoot)>,
+    time: Res<Time>,
+) {
+    for (entity, transform, mut shoot) in query.iter_mut() {
+        shoot.cooldown.tick(time.delta());
+        if !shoot.cooldown.finished() {
+            continue;
+        }
+
+        if shoot.times > 0 {
+            shoot.times -= 1;
+        } else if shoot.times == 0 {
+            commands.entity(entity).remove::<FanShoot>();
+            continue
+        }
+
+        let base_direction = shoot.velocity;
+
+        for i in 0..shoot.num_bullets {
+            let offset_index = i - (shoot.num_bullets - 1) / 2;
+            let angle_rad = (offset_index as f32) * shoot.angle_deg.to_radians();
+            let direction = Vec2::from_angle(angle_rad).rotate(base_direction);
+
+            let mut bullet_entity = commands.spawn((
+                StateScoped(AppState::InGame),
+                shoot.bullet.to_bundle(),
+                Transform::from_translation(transform.translation()),
+                Velocity::linear(direction),
+            ));
+
+            match shoot.bullet.clone().bullet_type {
+                BulletType::Normal => {
+                    shoot.bullet.bullet_type.insert_into(&mut bullet_entity);
+                }
+                BulletType::Homing(_) => {
+                    shoot.bullet.bullet_type.insert_into(&mut bullet_entity);
+                }
+                BulletType::Spiral(mut spiral) => {
+                    spiral.forward_velocity = direction.normalize_or_zero() * spiral.forward_velocity.length();
+                    BulletType::Spiral(spiral).insert_into(&mut bullet_entity);
+                }
+                BulletType::Laser(_) => {
+                    let rotation = Quat::from_rotation_z(direction.normalize_or_zero().to_angle());
+                    bullet_entity.insert(Transform {
+                        translation: transform.translation(),
+                        rotation,
+                        ..default()
+                    });
+                    bullet_entity.insert(CollisionGroups::new(Group::NONE, Group::NONE));
+                    bullet_entity.insert(Velocity::zero());
+                    shoot.bullet.bullet_type.insert_into(&mut bullet_entity);
+                }
+            }
+        }
+
+        shoot.cooldown.reset();
+    }
+}
+
+fn spawn_support_units(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+    powers: Res<PlayerPowers>,
+    character: Res<CharacterDef>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    support_query: Query<Entity, With<SupportUnit>>,
+) {
+    let support = &character.support;
+    if powers.0 < 1 || support_query.iter().count() >= support.offsets.len() {
+        return;
+    }
+
+    if let Ok((player_entity, _)) = player_query.get_single() {
+        for &(original_offset, focus_offset) in support.offsets.iter() {
+            commands.spawn((
+                StateScoped(AppState::InGame),
+                SupportUnit {
+                    original_position: original_offset,
+                    focus_position: focus_offset,
+                },
+                SingleShoot {
+                    bullet: shot_bullet_info(&support.shot, &font.0),
+                    velocity: Vec2::Y * support.shot.speed,
+                    cooldown: Timer::from_seconds(support.shot.cooldown, TimerMode::Repeating),
+                    times: -1,
+                },
+                BulletDamage(support.shot.damage),
+                Text2d::new(support.glyph),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size: 30.0,
+                    ..default()
+                },
+                Transform::from_translation(original_offset),
+                RigidBody::KinematicVelocityBased,
+                Velocity {
+                    angvel: support.spin * (-original_offset.x.signum()),
+                    ..default()
+                },
+                TextLayout::default(),
+                TextColor(support.color),
+            )).set_parent(player_entity);
+        }
+    }
+}
+
+fn despawn_support_units(
+    mut commands: Commands,
+    powers: Res<PlayerPowers>,
+    query: Query<Entity, With<SupportUnit>>,
+) {
+    if powers.0 < 1 {
+        for entity in query.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn bullet_info_from_def(
+    def: &BulletDef,
+    font: &Handle<Font>,
+) -> BulletInfo {
+    let text_font = TextFont {
+        font: font.clone(),
+        font_size: 30.0,
+        ..default()
+    };
+
+    match &def.kind {
+        BulletKindDef::Laser { telegraph, duration, length } => {
+            let laser_font_size = text_font.font_size;
+            let collider_x = laser_font_size / 3.5;
+            let collider_y = length / 2.0;
+
+            let laser_text = "!\n".repeat((length / laser_font_size / 1.2).floor() as usize);
+            let mut initial_color = color_from_hex(&def.color);
+            initial_color.set_alpha(0.0);
+
+            BulletInfo {
+                bullet_type: BulletType::Laser(LaserBullet {
+                    telegraph_duration: Timer::from_seconds(*telegraph, TimerMode::Once),
+                    duration: Timer::from_seconds(*duration, TimerMode::Once),
+                    animation_timer: Timer::from_seconds(0.2, TimerMode::Once),
+                    width: 1,
+                }),
+                target: BulletTarget::Player,
+                text: Text2d::new(laser_text),
+                text_font,
+                text_layout: Default::default(),
+                text_color: TextColor(initial_color),
+                collider: Collider::cuboid(collider_x, collider_y),
+            }
+        }
+        kind => {
+            let bullet_type = match kind {
+                BulletKindDef::Homing { speed, rotate_speed } => BulletType::Homing(HomingBullet {
+                    speed: *speed,
+                    rotate_speed: *rotate_speed,
+                }),
+                BulletKindDef::Spiral { radius, radius_growth, angular_speed, angle, forward_speed } => BulletType::Spiral(SpiralBullet {
+                    radius: *radius,
+                    radius_growth: *radius_growth,
+                    angular_speed: *angular_speed,
+                    angle: *angle,
+                    forward_velocity: Vec2::Y * *forward_speed,
+                }),
+                _ => BulletType::Normal,
+            };
+
+            BulletInfo {
+                bullet_type,
+                target: BulletTarget::Player,
+                text: Text2d::new(def.glyph.clone()),
+                text_font,
+                text_layout: Default::default(),
+                text_color: TextColor(color_from_hex(&def.color)),
+                collider: Collider::ball(5.0),
+            }
+        }
+    }
+}
+
+fn insert_emitter(
+    entity: &mut EntityCommands,
+    emitter: &EmitterDef,
+    origin: Vec2,
+    player_pos: Vec2,
+    font: &Handle<Font>,
+) {
+    let aim_direction = |aim: &AimDef| match aim {
+        AimDef::Player => (player_pos - origin).normalize_or_zero(),
+        AimDef::Fixed(direction) => direction.normalize_or_zero(),
+    };
+
+    match emitter {
+        EmitterDef::None => {}
+        EmitterDef::Single { bullet, aim, speed, cooldown, times } => {
+            entity.insert(SingleShoot {
+                bullet: bullet_info_from_def(bullet, font),
+                velocity: aim_direction(aim) * *speed,
+                cooldown: Timer::from_seconds(*cooldown, TimerMode::Repeating),
+                times: *times,
+            });
+        }
+        EmitterDef::Fan { bullet, aim, speed, num_bullets, angle_deg, cooldown, times } => {
+            entity.insert(FanShoot {
+                bullet: bullet_info_from_def(bullet, font),
+                num_bullets: *num_bullets,
+                angle_deg: *angle_deg,
+                velocity: aim_direction(aim) * *speed,
+                cooldown: Timer::from_seconds(*cooldown, TimerMode::Repeating),
+                times: *times,
+            });
+        }
+    }
+}
+
+fn spawn_wave_enemy(
+    commands: &mut Commands,
+    wave: &WaveDef,
+    spawn_pos: Vec2,
+    player_pos: Vec2,
+    font: &Handle<Font>,
+) {
+    let movement_vec = match wave.movement {
+        MovementDef::Linear(velocity) => velocity,
+        MovementDef::TowardPlayer(speed) => (player_pos - spawn_pos).normalize_or_zero() * speed,
+    };
+
+    let mut enemy_entity = commands.spawn((
+        Text2d::new(wave.glyph.clone()),
+        TextFont {
+            font: font.clone(),
+            font_size: 40.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        TextColor(color_from_hex(&wave.color)),
+
+        Enemy,
+        RigidBody::Dynamic,
+        Sensor,
+        GravityScale(0.0),
+        Collider::ball(10.0),
+        Velocity::zero(),
+        ActiveEvents::COLLISION_EVENTS,
+        CollisionGroups::new(Group::GROUP_4, Group::GROUP_1 | Group::GROUP_2),
+
+        Transform::from_translation(spawn_pos.extend(0.0)),
+        LinearMovement(movement_vec),
+        Health(wave.health),
+    ));
+    enemy_entity.insert((StateScoped(AppState::InGame), ScoreValue(wave.score)));
+
+    insert_emitter(&mut enemy_entity, &wave.emitter, spawn_pos, player_pos, font);
+}
+
+fn spawn_boss(
+    commands: &mut Commands,
+    boss: &BossDef,
+    player_pos: Vec2,
+    window: &WindowSize,
+    font: &Handle<Font>,
+) {
+    const ENTRY_INVINCIBLE_SECS: f32 = 2.0;
+
+    let Some(first_phase) = boss.phases.first() else { return; };
+    let spawn_pos = Vec2::new(boss.position.x, window.height / 2.0);
+
+    let boss_entity = commands.spawn((
+        StateScoped(AppState::InGame),
+        Text2d::new(boss.glyph.clone()),
+        TextFont {
+            font: font.clone(),
+            font_size: 50.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        TextColor(color_from_hex(&boss.color)),
+
+        Enemy,
+        Boss {
+            name: boss.name.clone(),
+            phases: boss.phases.clone(),
+            phase: 0,
+            timer: Timer::from_seconds(first_phase.timeout, TimerMode::Once),
+            home: boss.position,
+            score: boss.score,
+            phase_started: false,
+            failed: false,
+        },
+        Health(first_phase.health),
+        Invincible(Timer::from_seconds(ENTRY_INVINCIBLE_SECS, TimerMode::Once)),
+        Transform::from_translation(spawn_pos.extend(0.0)),
+    )).insert((
+        RigidBody::Dynamic,
+        Sensor,
+        GravityScale(0.0),
+        Collider::ball(20.0),
+        Velocity::zero(),
+        ActiveEvents::COLLISION_EVENTS,
+        CollisionGroups::new(Group::GROUP_4, Group::GROUP_1 | Group::GROUP_2),
+    )).id();
+
+    spawn_boss_emitters(commands, boss_entity, first_phase, boss.position, player_pos, font);
+}
+
+fn spawn_boss_emitters(
+    commands: &mut Commands,
+    boss_entity: Entity,
+    phase: &BossPhaseDef,
+    origin: Vec2,
+    player_pos: Vec2,
+    font: &Handle<Font>,
+) {
+    for emitter in phase.emitters.iter() {
+        let mut emitter_entity = commands.spawn((
+            StateScoped(AppState::InGame),
+            BossEmitter,
+            Transform::default(),
+        ));
+        insert_emitter(&mut emitter_entity, emitter, origin, player_pos, font);
+        emitter_entity.set_parent(boss_entity);
+    }
+}
+
+fn run_stage(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut runner: ResMut<StageRunner>,
+    stages: Res<StageList>,
+    stage_assets: Res<Assets<StageAsset>>,
+    enemy_query: Query<(), With<Enemy>>,
+    player_query: Query<&Transform, With<Player>>,
+    window: Res<WindowSize>,
+    font: Res<AsciiFont>,
+    difficulty: Res<DifficultyDef>,
+    mut boss_defeated: EventReader<BossDefeated>,
+    mut stage_finished: EventWriter<StageFinished>,
+    mut dialogue_cue: EventWriter<DialogueCue>,
+) {
+    if runner.finished {
+        return;
+    }
+    let Some(stage) = stages.get(runner.extra, runner.stage).and_then(|handle| stage_assets.get(handle)) else {
+        return;
+    };
+
+    let runner = &mut *runner;
+    // spell practice is only the fight, the script has nothing to say
+    let mut cue_dialogue = |path: &Option<String>, boss: Option<usize>| {
+        if runner.spell_practice.is_none()
+            && let Some(dialogue) = path.as_deref().and_then(|path| stage.dialogue(path))
+        {
+            dialogue_cue.send(DialogueCue { dialogue: dialogue.clone(), boss });
+        }
+    };
+    for _ in boss_defeated.read() {
+        runner.boss_active = false;
+        if let Some(index) = runner.current_boss() && let Some(boss) = stage.bosses.get(index) {
+            cue_dialogue(&boss.dialogue_after, Some(index));
+        }
+    }
+    // the stage clock stops while a boss is fighting
+    if runner.boss_active {
+        return;
+    }
+
+    if runner.schedule.is_none() {
+        cue_dialogue(&stage.dialogue, None);
+    }
+    let schedule = runner.schedule.get_or_insert_with(|| match runner.spell_practice {
+        Some(_) => Vec::new(),
+        None => stage.spawn_schedule(),
+    });
+    runner.elapsed += time.delta_secs();
+
+    let player_pos = player_query
+        .get_single()
+        .map(|transform| transform.translation.truncate())
+        .unwrap_or(PLAYER_RESPAWN_POS.truncate());
+
+    while let Some(next) = schedule.get(runner.cursor) {
+        if next.time > runner.elapsed {
+            break;
+        }
+        let wave = difficulty.scale_wave(&stage.waves[next.wave]);
+        spawn_wave_enemy(&mut commands, &wave, wave.spawns[next.spawn], player_pos, &font.0);
+        runner.cursor += 1;
+    }
+
+    let next_boss = match runner.spell_practice {
+        // spell practice brings its boss in right away, and only once
+        Some((boss, _)) if runner.next_boss <= boss => Some(boss),
+        Some(_) => None,
+        None => stage.bosses
+            .get(runner.next_boss)
+            .filter(|boss| boss.time <= runner.elapsed)
+            .map(|_| runner.next_boss),
+    };
+    if let Some(index) = next_boss && let Some(boss) = stage.bosses.get(index) {
+        let mut boss = difficulty.scale_boss(boss);
+        if let Some((_, phase)) = runner.spell_practice {
+            boss.phases = boss.phases.get(phase).cloned().into_iter().collect();
+        }
+        spawn_boss(&mut commands, &boss, player_pos, &window, &font.0);
+        cue_dialogue(&boss.dialogue_before, Some(index));
+        runner.next_boss = index + 1;
+        runner.boss_active = true;
+        return;
+    }
+
+    if runner.cursor >= schedule.len()
+        && (runner.spell_practice.is_some() || runner.next_boss >= stage.bosses.len())
+        && enemy_query.is_empty()
+    {
+        runner.finished = true;
+        info!("Stage \"{}\" finished", stage.name);
+        stage_finished.send(StageFinished { stage: runner.stage });
+    }
+}
+
+fn boss_movement(
+    mut query: Query<(&Boss, &Transform, &mut Velocity)>,
+) {
+    const APPROACH_RATE: f32 = 2.0;
+    for (boss, transform, mut velocity) in query.iter_mut() {
+        velocity.linvel = (boss.home - transform.translation.truncate()) * APPROACH_RATE;
+    }
+}
+
+fn boss_phase(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bosses: Query<(Entity, &mut Boss, &mut Health, &Transform, Option<&Children>)>,
+    emitters: Query<(), With<BossEmitter>>,
+    bullets: Query<(Entity, &BulletTarget)>,
+    player_query: Query<&Transform, With<Player>>,
+    font: Res<AsciiFont>,
+    mut score: ResMut<Score>,
+    mut rng: ResMut<GameRng>,
+    mut cosmetic: ResMut<CosmeticRng>,
+    mut boss_defeated: EventWriter<BossDefeated>,
+    mut spell_card_declared: EventWriter<SpellCardDeclared>,
+    mut spell_card_captured: EventWriter<SpellCardCaptured>,
+) {
+    const PHASE_INVINCIBLE_SECS: f32 = 1.5;
+    const PHASE_BONUS: u64 = 10000;
+    const SPELL_CARD_BONUS: u64 = 100000;
+
+    let player_pos = player_query
+        .get_single()
+        .map(|transform| transform.translation.truncate())
+        .unwrap_or(PLAYER_RESPAWN_POS.truncate());
+
+    for (boss_entity, mut boss, mut health, transform, children) in bosses.iter_mut() {
+        boss.timer.tick(time.delta());
+        if !boss.phase_started {
+            boss.phase_started = true;
+            boss.failed = false;
+            if let Some(spell_card) = boss.phases.get(boss.phase).and_then(|phase| phase.spell_card.clone()) {
+                spell_card_declared.send(SpellCardDeclared { name: spell_card });
+            }
+        }
+        if health.0 > 0 && !boss.timer.finished() {
+            continue;
+        }
+
+        if health.0 <= 0 {
+            match boss.phases.get(boss.phase).and_then(|phase| phase.spell_card.clone()) {
+                None => score.0 += PHASE_BONUS,
+                Some(spell_card) if !boss.failed => {
+                    // captured, the bonus shrinks as the timer runs down
+                    let remaining = boss.timer.remaining_secs() / boss.timer.duration().as_secs_f32().max(f32::EPSILON);
+                    score.0 += (SPELL_CARD_BONUS as f32 * (0.5 + 0.5 * remaining)) as u64;
+                    spell_card_captured.send(SpellCardCaptured { name: spell_card });
+                }
+                Some(_) => {}
+            }
+        }
+        boss.phase_started = false;
+
+        // phase over: clear the field and drop the old pattern
+        for (bullet_entity, target) in bullets.iter() {
+            if matches!(target, BulletTarget::Player) {
+                commands.entity(bullet_entity).despawn();
+            }
+        }
+        if let Some(children) = children {
+            for &child in children.iter() {
+                if emitters.get(child).is_ok() {
+                    commands.entity(child).despawn();
+                }
+            }
+        }
+
+        boss.phase += 1;
+        let Some(phase) = boss.phases.get(boss.phase).cloned() else {
+            // boss defeated
+            score.0 += boss.score;
+            spawn_enemy_drops(&mut commands, &font.0, &mut rng, transform.translation, 8, 12);
+            spawn_enemy_death_particles(&mut commands, &font.0, &mut cosmetic, transform.translation, 48);
+            boss_defeated.send(BossDefeated {
+                name: boss.name.clone(),
+                position: transform.translation.truncate(),
+            });
+            commands.entity(boss_entity).despawn_recursive();
+            continue;
+        };
+
+        health.0 = phase.health;
+        boss.timer = Timer::from_seconds(phase.timeout, TimerMode::Once);
+        commands.entity(boss_entity).insert(Invincible(Timer::from_seconds(PHASE_INVINCIBLE_SECS, TimerMode::Once)));
+        spawn_boss_emitters(&mut commands, boss_entity, &phase, boss.home, player_pos, &font.0);
+    }
+}
+
+fn update_boss_ui(
+    bosses: Query<(&Boss, &Health)>,
+    mut bar_query: Query<&mut Text2d, (With<BossHealthBarText>, Without<BossTimerText>)>,
+    mut timer_query: Query<&mut Text2d, (With<BossTimerText>, Without<BossHealthBarText>)>,
+) {
+    const BAR_LENGTH: usize = 30;
+
+    let (bar, timer) = match bosses.get_single() {
+        Ok((boss, health)) => {
+            let max_health = boss.phases.get(boss.phase).map(|phase| phase.health).unwrap_or(1).max(1);
+            let ratio = (health.0.max(0) as f32 / max_health as f32).clamp(0.0, 1.0);
+            let filled = (ratio * BAR_LENGTH as f32).ceil() as usize;
+            let remaining_phases = boss.phases.len().saturating_sub(boss.phase + 1);
+
+            let bar = format!(
+                "{} {}\n[{}{}]",
+                boss.name,
+                "*".repeat(remaining_phases),
+                "#".repeat(filled),
+                "-".repeat(BAR_LENGTH - filled),
+            );
+            let seconds = boss.timer.remaining_secs().ceil() as u32;
+            let timer = match boss.phases.get(boss.phase).and_then(|phase| phase.spell_card.as_ref()) {
+                Some(spell_card) => format!("{}\n{:02}", spell_card, seconds),
+                None => format!("{:02}", seconds),
+            };
+            (bar, timer)
+        }
+        Err(_) => (String::new(), String::new()),
+    };
+
+    for mut text in bar_query.iter_mut() {
+        if text.0 != bar {
+            text.0 = bar.clone();
+        }
+    }
+    for mut text in timer_query.iter_mut() {
+        if text.0 != timer {
+            text.0 = timer.clone();
+        }
+    }
+}
+
+fn item_gravity(
+    mut query: Query<&mut Velocity, (Or<(With<PowerItem>, With<PointItem>)>, Without<AutoCollect>)>,
+    time: Res<Time>,
+) {
+    let gravity_acc = -100.0;
+    let max_fall_speed = -100.0;
+    let horizontal_decay = 10.0;
+
+    for mut velocity in query.iter_mut() {
+        velocity.linvel.y += gravity_acc * time.delta_secs();
+        if velocity.linvel.y < max_fall_speed {
+            velocity.linvel.y = max_fall_speed;
+        }
+
+        if velocity.linvel.x.abs() > 0.0 {
+            let decay = horizontal_decay * time.delta_secs();
+            if velocity.linvel.x > 0.0 {
+                velocity.linvel.x = (velocity.linvel.x - decay).max(0.0);
+            } else {
+                velocity.linvel.x = (velocity.linvel.x + decay).min(0.0);
+            }
+        }
+    }
+}
+
+fn enemy_hit_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut EnemyHitParticle, &mut TextColor)>,
+) {
+    for (entity, mut timer, mut color) in query.iter_mut() {
+        timer.0.tick(time.delta());
+
+        let progress = timer.0.elapsed_secs() / timer.0.duration().as_secs_f32();
+        let alpha = (1.0 - progress.powf(2.0)).clamp(0.0, 1.0);
+
+        color.0.set_alpha(alpha);
+
+        if timer.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn enemy_death_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut EnemyDeathParticle, &mut TextColor, &mut Velocity)>,
+) {
+    const DECAY_COEFFICIENT: f32 = 1.5;
+    for (entity, mut timer, mut color, mut velocity) in query.iter_mut() {
+        timer.0.tick(time.delta());
+
+        let progress = timer.0.elapsed_secs() / timer.0.duration().as_secs_f32();
+        let alpha = (1.0 - progress).clamp(0.0, 1.0);
+
+        color.0.set_alpha(alpha);
+
+        let decay = 1.0 - time.delta_secs() * DECAY_COEFFICIENT;
+        velocity.linvel *= decay.clamp(0.0, 1.0);
+        velocity.angvel *= decay.clamp(0.0, 1.0);
+
+        if timer.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+fn player_death_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut PlayerDeathParticle, &mut TextColor, &mut Velocity)>,
+) {
+    const LINVEL_DECAY_COEFFICIENT: f32 = 0.2;
+    const ANGVEL_DECAY_COEFFICIENT: f32 = 1.5;
+    for (entity, mut timer, mut color, mut velocity) in query.iter_mut() {
+        timer.0.tick(time.delta());
+
+        let progress = timer.0.elapsed_secs() / timer.0.duration().as_secs_f32();
+        color.0.set_alpha((1.0 - progress).clamp(0.0, 1.0));
+
+        let linvel_decay = 1.0 - time.delta_secs() * LINVEL_DECAY_COEFFICIENT;
+        let angvel_decay = 1.0 - time.delta_secs() * ANGVEL_DECAY_COEFFICIENT;
+        velocity.linvel *= linvel_decay.clamp(0.0, 1.0);
+        velocity.angvel *= angvel_decay.clamp(0.0, 1.0);
+
+        if timer.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn tick_invincibility(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invincible, &mut Visibility)>,
+) {
+    const BLINK_FREQ: f32 = 10.0;
+    for (entity, mut inv, mut visibility) in query.iter_mut() {
+        inv.0.tick(time.delta());
+
+        let phase = inv.0.elapsed_secs() * BLINK_FREQ * std::f32::consts::TAU;
+        let blink_on = phase.sin() >= 0.0;
+
+        *visibility = if blink_on {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+
+        if inv.0.finished() {
+            commands.entity(entity).remove::<Invincible>();
+            *visibility = Visibility::Visible;
+        }
+    }
+}
+
+fn match_bullet_hit_pair<
+    Target: Component,
+    D: QueryData
+>(
+    entity1: Entity,
+    entity2: Entity,
+    bullets: &Query<(Entity, &BulletTarget, &Transform)>,
+    targets: &Query<D, With<Target>>,
+) -> Option<(Entity, Entity)> {
+    if bullets.get(entity1).is_ok() && targets.get(entity2).is_ok() {
+        Some((entity1, entity2))
+    } else if bullets.get(entity2).is_ok() && targets.get(entity1).is_ok() {
+        Some((entity2, entity1))
+    } else {
+        None
+    }
+}
+
+fn spawn_enemy_drops(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    rng: &mut GameRng,
+    translation: Vec3,
+    power_count: u32,
+    point_count: u32,
+) {
+    const ITEM_SPEED: f32 = 50.0;
+
+    for _ in 0..power_count {
+        commands.spawn((
+            StateScoped(AppState::InGame),
+            PowerItem,
+
+            Sprite::from_color(Color::Srgba(RED_400), Vec2::new(20.0, 20.0)),
+            Transform::from_translation(translation.xy().extend(-1.0)),
+
+            Collider::ball(8.0),
+            RigidBody::KinematicVelocityBased,
+            Velocity::linear(Vec2::new(
+                rng.gen_range(-0.5..0.5) * ITEM_SPEED,
+                150.0 + rng.gen_range(0.0..1.0) * 50.0
+            )),
+            ActiveEvents::COLLISION_EVENTS,
+            CollisionGroups::new(Group::GROUP_6, Group::GROUP_1),
+        )).with_children(|builder| {
+            builder.spawn((
+                Text2d::new("P"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 25.0,
+                    ..default()
+                },
+                TextLayout::default(),
+                TextColor(Color::Srgba(WHITE)),
+                Transform::from_translation(Vec3::Z),
+            ));
+        });
+    }
+
+    for _ in 0..point_count {
+        commands.spawn((
+            StateScoped(AppState::InGame),
+            PointItem,
+
+            Sprite::from_color(Color::Srgba(BLUE_400), Vec2::new(20.0, 20.0)),
+            Transform::from_translation(translation.xy().extend(-3.0)),
+            RigidBody::KinematicVelocityBased,
+            Collider::ball(8.0),
+            Velocity::linear(Vec2::new(
+                rng.gen_range(-0.5..0.5) * ITEM_SPEED,
+                150.0 + rng.gen_range(0.0..1.0) * 50.0
+            )),
+            CollisionGroups::new(Group::GROUP_6, Group::GROUP_1),
+            ActiveEvents::COLLISION_EVENTS,
+        )).with_children(|builder| {
+            builder.spawn((
+                Text2d::new("%"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 25.0,
+                    ..default()
+                },
+                TextLayout::default(),
+                TextColor(Color::Srgba(WHITE)),
+                Transform::from_translation(Vec3::Z),
+            ));
+        });
+    }
+}
+
+fn spawn_enemy_death_particles(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    cosmetic: &mut CosmeticRng,
+    translation: Vec3,
+    num_particles: u32,
+) {
+    for _ in 0..num_particles {
+        let char = if cosmetic.gen_bool(0.5) { "0" } else { "1" };
+        let gray = cosmetic.gen_range(0.0..1.0);
+        let angle = cosmetic.gen_range(0.0..1.0) * std::f32::consts::TAU;
+        let speed = cosmetic.gen_range(0.0..1.0) * 50.0 + 50.0;
+        let dir = Vec2::from_angle(angle) * speed;
+
+        commands.spawn((
+            StateScoped(AppState::InGame),
+            EnemyDeathParticle(Timer::from_seconds(cosmetic.gen_range(0.0..1.0) * 2.0 + 1.0, TimerMode::Once)),
+            Text2d::new(char),
+            TextFont {
+                font: font.clone(),
+                font_size: 20.0,
+                ..default()
+            },
+            TextLayout::default(),
+            TextColor(Color::srgba(gray, gray, gray, 1.0)),
+            Transform::from_translation(translation),
+            RigidBody::KinematicVelocityBased,
+            Velocity {
+                linvel: dir,
+                angvel: cosmetic.gen_range(0.0..1.0) * 10.0 - 2.0,
+            },
+        ));
+    }
+}
+
+fn kill_enemy(
+    commands: &mut Commands,
+    enemy_killed: &mut EventWriter<EnemyKilled>,
+    enemy_entity: Entity,
+    position: Vec2,
+    score: u64,
+) {
+    commands.entity(enemy_entity).despawn();
+    enemy_killed.send(EnemyKilled {
+        entity: enemy_entity,
+        position,
+        score,
+    });
+}
+
+fn drop_enemy_items(
+    mut commands: Commands,
+    mut events: EventReader<EnemyKilled>,
+    mut rng: ResMut<GameRng>,
+    font: Res<AsciiFont>,
+) {
+    for event in events.read() {
+        let power_count = rng.gen_range(1..=3);
+        let point_count = rng.gen_range(1..=3);
+        spawn_enemy_drops(&mut commands, &font.0, &mut rng, event.position.extend(0.0), power_count, point_count);
+    }
+}
+
+fn spawn_enemy_killed_particles(
+    mut commands: Commands,
+    mut events: EventReader<EnemyKilled>,
+    mut cosmetic: ResMut<CosmeticRng>,
+    font: Res<AsciiFont>,
+) {
+    for event in events.read() {
+        let num_particles = cosmetic.gen_range(8..=16);
+        spawn_enemy_death_particles(&mut commands, &font.0, &mut cosmetic, event.position.extend(0.0), num_particles);
+    }
+}
+
+fn score_enemy_kills(
+    mut events: EventReader<EnemyKilled>,
+    mut score: ResMut<Score>,
+) {
+    for event in events.read() {
+        score.0 += event.score;
+    }
+}
+
+fn spawn_enemy_hit_particle(
+    mut commands: Commands,
+    mut events: EventReader<EnemyHit>,
+    mut cosmetic: ResMut<CosmeticRng>,
+    font: Res<AsciiFont>,
+) {
+    for event in events.read() {
+        let chars = ["(", ")", "<", ">", "{", "}", "[", "]"];
+        let random_char = chars[cosmetic.gen_range(0..chars.len())];
+        let random_rotation = Quat::from_rotation_z(cosmetic.gen_range(0.0..1.0) * std::f32::consts::TAU);
+
+        let gray = 0.3 + cosmetic.gen_range(0.0..1.0) * 0.3;
+        let random_color = Color::srgb(gray, gray, gray);
+
+        commands.spawn((
+            StateScoped(AppState::InGame),
+            EnemyHitParticle(Timer::from_seconds(0.5, TimerMode::Once)),
+            Text2d::new(random_char),
+            TextFont {
+                font: font.0.clone(),
+                font_size: 45.0,
+                ..default()
+            },
+            TextLayout::default(),
+            TextColor(random_color),
+            Transform {
+                translation: event.position.extend(-5.0), // 在子弹位置
+                rotation: random_rotation,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn bullet_hit(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+
+    mut enemies: Query<(Entity, &mut Health, &Transform, Option<&Invincible>, Has<Boss>), With<Enemy>>,
+    player: Query<(Entity, &Transform, Option<&Invincible>), With<Player>>,
+    bullets: Query<(Entity, &BulletTarget, &Transform)>,
+    bullet_damages: Query<&BulletDamage>,
+    piercing_bullets: Query<(), With<Piercing>>,
+    score_values: Query<&ScoreValue>,
+
+    mut enemy_hit: EventWriter<EnemyHit>,
+    mut enemy_killed: EventWriter<EnemyKilled>,
+    mut player_hit: EventWriter<PlayerHit>,
+) {
+    for event in collision_events.read() {
+        match event {
+            CollisionEvent::Started(entity1, entity2, _) => {
+                if let Some((bullet_entity, enemy_entity)) =
+                    match_bullet_hit_pair::<
+                        Enemy,
+                        (Entity, &mut Health, &Transform, Option<&Invincible>, Has<Boss>)
+                    >(*entity1, *entity2, &bullets, &enemies)
+                {
+                    if let Ok((enemy_ent, mut health, transform, invincible, is_boss)) = enemies.get_mut(enemy_entity) {
+                        if invincible.is_some() {
+                            continue
+                        }
+                        let damage = bullet_damages.get(bullet_entity).map(|damage| damage.0).unwrap_or(1);
+                        health.0 -= damage;
+
+                        if let Ok((_, _, bullet_transform)) = bullets.get(bullet_entity) {
+                            enemy_hit.send(EnemyHit {
+                                entity: enemy_ent,
+                                position: bullet_transform.translation.truncate(),
+                                damage,
+                            });
+                        }
+
+                        // enemy death, bosses move on to their next phase in boss_phase instead
+                        if health.0 <= 0 && !is_boss {
+                            let score = score_values.get(enemy_ent).map(|value| value.0).unwrap_or(0);
+                            kill_enemy(&mut commands, &mut enemy_killed, enemy_ent, transform.translation.truncate(), score);
+                        }
+                    }
+                    if piercing_bullets.get(bullet_entity).is_err() {
+                        commands.entity(bullet_entity).despawn();
+                    }
+                } else if let Some((bullet_entity, player_entity )) =
+                    match_bullet_hit_pair::<
+                        Player,
+                        (Entity, &Transform, Option<&Invincible>)
+                    >(*entity1, *entity2, &bullets, &player)
+                {
+                    // player death
+                    if let Ok((_, player_transform, invincible)) = player.get_single() {
+
+                        if invincible.is_some() {
+                            continue
+                        }
+
+                        player_hit.send(PlayerHit {
+                            entity: player_entity,
+                            position: player_transform.translation.truncate(),
+                        });
+                    }
+
+                    commands.entity(player_entity)
+                        .insert(Visibility::Hidden)
+                        .insert(Invincible(Timer::from_seconds(3.0, TimerMode::Once)))
+                        .insert(Transform::from_translation(PLAYER_RESPAWN_POS));
+                    commands.entity(bullet_entity).despawn();
+                }
+
+            }
+            _ => {}
+        }
+    }
+}
+
+fn lose_life(
+    mut events: EventReader<PlayerHit>,
+    mut lives: ResMut<PlayerLives>,
+    mut bosses: Query<&mut Boss>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    for _ in events.read() {
+        for mut boss in bosses.iter_mut() {
+            boss.failed = true;
+        }
+        if lives.0 <= 0 {
+            next_game_state.set(GameState::GameOver);
+        } else {
+            lives.0 -= 1;
+        }
+    }
+}
+
+fn drop_player_power(
+    mut commands: Commands,
+    mut events: EventReader<PlayerHit>,
+    mut powers: ResMut<PlayerPowers>,
+    mut rng: ResMut<GameRng>,
+    font: Res<AsciiFont>,
+) {
+    for event in events.read() {
+        let dropped_power = (powers.0 as f32 * 0.5).ceil() as u32;
+        powers.0 = 0;
+
+        for _ in 0..dropped_power {
+            let base_angle = std::f32::consts::FRAC_PI_2;
+            let spread_range = std::f32::consts::FRAC_PI_8;
+
+            let angle = base_angle + rng.gen_range(-0.5..0.5) * 2.0 * spread_range;
+            let speed = rng.gen_range(0.0..1.0) * 100.0 + 100.0;
+            let dir = Vec2::from_angle(angle) * speed;
+
+            commands.spawn((
+                StateScoped(AppState::InGame),
+                PowerItem,
+                Sprite::from_color(Color::Srgba(RED_400), Vec2::new(20.0, 20.0)),
+                Transform::from_translation(event.position.extend(-1.0)),
+                Collider::ball(8.0),
+                RigidBody::KinematicVelocityBased,
+                Velocity::linear(dir),
+                ActiveEvents::COLLISION_EVENTS,
+                CollisionGroups::new(Group::GROUP_6, Group::GROUP_1),
+            )).with_children(|builder| {
+                builder.spawn((
+                    Text2d::new("P"),
+                    TextFont {
+                        font: font.0.clone(),
+                        font_size: 25.0,
+                        ..default()
+                    },
+                    TextLayout::default(),
+                    TextColor(Color::Srgba(WHITE)),
+                    Transform::from_translation(Vec3::Z),
+                ));
+            });
+        }
+    }
+}
+
+fn spawn_player_hit_particles(
+    mut commands: Commands,
+    mut events: EventReader<PlayerHit>,
+    mut cosmetic: ResMut<CosmeticRng>,
+    font: Res<AsciiFont>,
+) {
+    for event in events.read() {
+        let num_particles = cosmetic.gen_range(8..12);
+        for _ in 0..num_particles {
+            let hex_str = format!("0x{:02X}", cosmetic.gen_range(0..=u8::MAX));
+            let hue = 90.0 + cosmetic.gen_range(0.0..1.0) * 60.0;
+            let saturation = 0.6 + cosmetic.gen_range(0.0..1.0) * 0.4;
+            let lightness = 0.4 + cosmetic.gen_range(0.0..1.0) * 0.4;
+            let color = Color::hsl(hue, saturation, lightness);
+
+            let angle = cosmetic.gen_range(0.0..1.0) * std::f32::consts::TAU;
+            let speed = cosmetic.gen_range(0.0..1.0) * 50.0 + 80.0;
+            let dir = Vec2::from_angle(angle) * speed;
+
+            commands.spawn((
+                StateScoped(AppState::InGame),
+                PlayerDeathParticle(Timer::from_seconds(cosmetic.gen_range(0.0..1.0) * 1.5 + 2.0, TimerMode::Once)),
+                Text2d::new(hex_str),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextLayout::default(),
+                TextColor(color),
+                Transform::from_translation(event.position.extend(2.0)),
+                RigidBody::KinematicVelocityBased,
+                Velocity {
+                    linvel: dir,
+                    angvel: cosmetic.gen_range(0.0..1.0) * 10.0 - 5.0,
+                },
+            ));
+        }
+    }
+}
+
+fn item_hit(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    players: Query<Entity, With<Player>>,
+    power_items: Query<Entity, With<PowerItem>>,
+    point_items: Query<(&Transform, Has<AutoCollect>), With<PointItem>>,
+    transforms: Query<&Transform>,
+    window: Res<WindowSize>,
+    mut item_collected: EventWriter<ItemCollected>,
+) {
+    const POINT_ITEM_MAX_VALUE: u64 = 10000;
+    const POINT_ITEM_MIN_VALUE: u64 = 1000;
+    // same line as the auto collection zone in attract_items
+    const TOP_ZONE_HEIGHT: f32 = 150.0;
+
+    for event in collision_events.read() {
+        if let CollisionEvent::Started(entity1, entity2, _) = event {
+            let (_, item_entity) = if players.get(*entity1).is_ok() {
+                (*entity1, *entity2)
+            } else if players.get(*entity2).is_ok() {
+                (*entity2, *entity1)
+            } else {
+                continue;
+            };
+
+            if power_items.get(item_entity).is_ok() {
+                let position = transforms.get(item_entity).map(|transform| transform.translation.truncate()).unwrap_or_default();
+                item_collected.send(ItemCollected {
+                    item: item_entity,
+                    kind: ItemKind::Power,
+                    position,
+                    value: 0,
+                });
+                commands.entity(item_entity).despawn_recursive();
+            } else if let Ok((transform, auto_collect)) = point_items.get(item_entity) {
+                let bottom = -window.height / 2.0;
+                let top = window.height / 2.0 - TOP_ZONE_HEIGHT;
+                let height = ((transform.translation.y - bottom) / (top - bottom)).clamp(0.0, 1.0);
+                let value = if auto_collect {
+                    POINT_ITEM_MAX_VALUE
+                } else {
+                    POINT_ITEM_MIN_VALUE + ((POINT_ITEM_MAX_VALUE - POINT_ITEM_MIN_VALUE) as f32 * height) as u64
+                };
+                item_collected.send(ItemCollected {
+                    item: item_entity,
+                    kind: ItemKind::Point,
+                    position: transform.translation.truncate(),
+                    value,
+                });
+                commands.entity(item_entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn collect_item(
+    mut events: EventReader<ItemCollected>,
+    mut powers: ResMut<PlayerPowers>,
+    mut points: ResMut<PlayerPoints>,
+    mut score: ResMut<Score>,
+) {
+    for event in events.read() {
+        match event.kind {
+            ItemKind::Power => powers.0 += 1,
+            ItemKind::Point => points.0 += 1,
+        }
+        score.0 += event.value;
+    }
+}
+
+fn update_debug_overlay(
+    debug_state: Res<ShowColliderDebug>,
+    rng: Res<GameRng>,
+    mut query: Query<(&mut Text2d, &mut Visibility), With<DebugOverlayText>>,
+) {
+    let seed = format!("seed: {}", rng.seed());
+    for (mut text, mut visibility) in query.iter_mut() {
+        if text.0 != seed {
+            text.0 = seed.clone();
+        }
+        let target = if debug_state.0 { Visibility::Visible } else { Visibility::Hidden };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+}
+
+fn tick_cooldown_timer(
+    mut query: Query<&mut ShootCooldown>,
+    time: Res<Time>,
+) {
+    for mut cooldown in query.iter_mut() {
+        cooldown.0.tick(time.delta());
+    }
+}
+
+fn despawn_out_of_bounds<'a>(
+    commands: &mut Commands,
+    entities: impl Iterator<Item = (Entity, &'a Transform)>,
+    window: &WindowSize,
+    extra_margin: f32,
+) {
+    let max_x = window.width / 2.0 + extra_margin;
+    let max_y = window.height / 2.0 + extra_margin;
+
+    for (entity, transform) in entities {
+        let pos = transform.translation;
+        if pos.x.abs() > max_x || pos.y.abs() > max_y {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn despawn_bullets(
+    mut commands: Commands,
+    query: Query<
+        (Entity, &Transform),
+        (With<BulletTarget>, Without<LaserBullet>)
+    >,
+    window: Res<WindowSize>,
+) {
+    despawn_out_of_bounds(&mut commands, query.iter(), &window, 0.0);
+}
+
+fn despawn_items(
+    mut commands: Commands,
+    query: Query<
+        (Entity, &Transform),
+        Or<(
+            With<PowerItem>,
+            With<PointItem>,
+        )>
+    >,
+    window: Res<WindowSize>,
+) {
+    despawn_out_of_bounds(&mut commands, query.iter(), &window, 200.0);
+}
+
+fn despawn_enemies(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform), With<Enemy>>,
+    window: Res<WindowSize>,
+) {
+    despawn_out_of_bounds(&mut commands, query.iter(), &window, 100.0);
+}
+
+const BOMB_DURATION: f32 = 3.0;
+fn spawn_bomb_ring(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    position: Vec2,
+    radius: f32,
+    chars: &[&str],
+    color: Color,
+) {
+    const NUM_PARTICLES: usize = 24;
+    for i in 0..NUM_PARTICLES {
+        let angle = i as f32 / NUM_PARTICLES as f32 * std::f32::consts::TAU;
+        let dir = Vec2::from_angle(angle) * (radius / BOMB_DURATION * 2.0);
+
+        commands.spawn((
+            StateScoped(AppState::InGame),
+            BombParticle(Timer::from_seconds(BOMB_DURATION * 0.5, TimerMode::Once)),
+            Text2d::new(chars[i % chars.len()]),
+            TextFont {
+                font: font.clone(),
+                font_size: 40.0,
+                ..default()
+            },
+            TextLayout::default(),
+            TextColor(color),
+            Transform::from_translation(position.extend(3.0)),
+            RigidBody::KinematicVelocityBased,
+            Velocity {
+                linvel: dir,
+                angvel: 4.0,
+            },
+        ));
+    }
+}
+
+fn spawn_bomb_bullet(
+    commands: &mut Commands,
+    bullet: &BulletInfo,
+    position: Vec2,
+    velocity: Vec2,
+    damage: i32,
+) {
+    let mut bullet_entity = commands.spawn((
+        StateScoped(AppState::InGame),
+        bullet.to_bundle(),
+        Transform::from_translation(position.extend(2.0)),
+        Velocity::linear(velocity),
+        BulletDamage(damage),
+    ));
+    bullet.bullet_type.insert_into(&mut bullet_entity);
+}
+
+fn spawn_fantasy_orbs(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    position: Vec2,
+) {
+    const NUM_ORBS: usize = 6;
+    const ORB_SPEED: f32 = 300.0;
+    let colors = [RED_500, ORANGE_400, YELLOW_300, GREEN_400, BLUE_400, PURPLE_400];
+
+    for i in 0..NUM_ORBS {
+        let angle = std::f32::consts::FRAC_PI_2 + i as f32 / NUM_ORBS as f32 * std::f32::consts::TAU;
+        let orb = BulletInfo {
+            bullet_type: BulletType::Homing(HomingBullet {
+                speed: ORB_SPEED,
+                rotate_speed: 4.0,
+            }),
+            target: BulletTarget::Enemy,
+            text: Text2d::new("O"),
+            text_font: TextFont {
+                font: font.clone(),
+                font_size: 60.0,
+                ..default()
+            },
+            text_layout: TextLayout::new_with_justify(JustifyText::Center),
+            text_color: TextColor(Color::from(Color::Srgba(colors[i % colors.len()]).to_linear() * 3.0)),
+            collider: Collider::ball(25.0),
+        };
+        spawn_bomb_bullet(commands, &orb, position, Vec2::from_angle(angle) * ORB_SPEED, 20);
+    }
+}
+
+fn spawn_homing_amulets(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    position: Vec2,
+) {
+    const NUM_AMULETS: usize = 16;
+    const AMULET_SPEED: f32 = 600.0;
+
+    for i in 0..NUM_AMULETS {
+        let angle = i as f32 / NUM_AMULETS as f32 * std::f32::consts::TAU;
+        let amulet = BulletInfo {
+            bullet_type: BulletType::Homing(HomingBullet {
+                speed: AMULET_SPEED,
+                rotate_speed: 6.0,
+            }),
+            target: BulletTarget::Enemy,
+            text: Text2d::new("="),
+            text_font: TextFont {
+                font: font.clone(),
+                font_size: 40.0,
+                ..default()
+            },
+            text_layout: TextLayout::new_with_justify(JustifyText::Center),
+            text_color: TextColor(Color::from(Color::Srgba(RED_400).to_linear() * 2.0)),
+            collider: Collider::ball(10.0),
+        };
+        spawn_bomb_bullet(commands, &amulet, position, Vec2::from_angle(angle) * AMULET_SPEED, 8);
+    }
+}
+
+const MASTER_SPARK_COLUMNS: usize = 9;
+const MASTER_SPARK_FONT_SIZE: f32 = 40.0;
+fn spawn_master_spark(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    position: Vec2,
+    window: &WindowSize,
+) -> f32 {
+    let length = window.height * 1.5;
+    let half_width = MASTER_SPARK_COLUMNS as f32 * MASTER_SPARK_FONT_SIZE * 0.6 / 2.0;
+    let rows = (length / MASTER_SPARK_FONT_SIZE / 1.2).floor() as usize;
+    let offset = Vec3::new(0.0, length / 2.0 + 20.0, 2.0);
+
+    let mut initial_color = Color::from(Color::Srgba(YELLOW_200).to_linear() * 3.0);
+    initial_color.set_alpha(0.0);
+
+    let spark = BulletInfo {
+        bullet_type: BulletType::Laser(LaserBullet {
+            telegraph_duration: Timer::from_seconds(0.3, TimerMode::Once),
+            duration: Timer::from_seconds(BOMB_DURATION - 0.3, TimerMode::Once),
+            animation_timer: Timer::from_seconds(0.2, TimerMode::Once),
+            width: MASTER_SPARK_COLUMNS,
+        }),
+        target: BulletTarget::Enemy,
+        text: Text2d::new(format!("{}\n", "!".repeat(MASTER_SPARK_COLUMNS)).repeat(rows)),
+        text_font: TextFont {
+            font: font.clone(),
+            font_size: MASTER_SPARK_FONT_SIZE,
+            ..default()
+        },
+        text_layout: TextLayout::new_with_justify(JustifyText::Center),
+        text_color: TextColor(initial_color),
+        collider: Collider::cuboid(half_width, length / 2.0),
+    };
+
+    let mut spark_entity = commands.spawn((
+        StateScoped(AppState::InGame),
+        spark.to_bundle(),
+        Transform::from_translation(position.extend(0.0) + offset),
+        Velocity::zero(),
+        BulletDamage(5),
+        Piercing,
+        FollowPlayer(offset),
+    ));
+    // collision starts with the laser once the telegraph is over
+    spark_entity.insert(CollisionGroups::new(Group::NONE, Group::NONE));
+    spark.bullet_type.insert_into(&mut spark_entity);
+
+    half_width
+}
+
+fn spawn_stardust(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    position: Vec2,
+) {
+    const NUM_ARMS: usize = 6;
+    const STARS_PER_ARM: usize = 5;
+    let colors = [YELLOW_200, SKY_300, PINK_300, GREEN_300];
+
+    for arm in 0..NUM_ARMS {
+        for i in 0..STARS_PER_ARM {
+            let angle = (arm as f32 + i as f32 * 0.15) / NUM_ARMS as f32 * std::f32::consts::TAU;
+            let outward = Vec2::from_angle(angle) * (150.0 + i as f32 * 60.0);
+            let star = BulletInfo {
+                bullet_type: BulletType::Spiral(SpiralBullet {
+                    radius: 20.0,
+                    radius_growth: 40.0,
+                    angular_speed: 3.0,
+                    angle,
+                    forward_velocity: outward,
+                }),
+                target: BulletTarget::Enemy,
+                text: Text2d::new("*"),
+                text_font: TextFont {
+                    font: font.clone(),
+                    font_size: 40.0,
+                    ..default()
+                },
+                text_layout: TextLayout::new_with_justify(JustifyText::Center),
+                text_color: TextColor(Color::from(Color::Srgba(colors[(arm + i) % colors.len()]).to_linear() * 2.5)),
+                collider: Collider::ball(12.0),
+            };
+            spawn_bomb_bullet(commands, &star, position, outward, 4);
+        }
+    }
+}
+
+fn player_bomb(
+    mut commands: Commands,
+    bombs: Res<PlayerBombs>,
+    spell_card: Res<PlayerSpellCard>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    active_bombs: Query<(), With<ActiveBomb>>,
+    window: Res<WindowSize>,
+    font: Res<AsciiFont>,
+    mut bomb_used: EventWriter<BombUsed>,
+) {
+    const INVINCIBLE_SECS: f32 = BOMB_DURATION + 1.0;
+
+    if bombs.0 <= 0 || !active_bombs.is_empty() {
+        return;
+    }
+    let Ok((player_entity, player_transform)) = player_query.get_single() else {
+        return;
+    };
+
+    commands.entity(player_entity)
+        .insert(Invincible(Timer::from_seconds(INVINCIBLE_SECS, TimerMode::Once)));
+
+    let player_pos = player_transform.translation.truncate();
+    bomb_used.send(BombUsed {
+        spell_card: spell_card.0,
+        position: player_pos,
+    });
+    let (area, damage) = match spell_card.0 {
+        SpellCard::FantasyOrb => {
+            spawn_fantasy_orbs(&mut commands, &font.0, player_pos);
+            spawn_bomb_ring(&mut commands, &font.0, player_pos, 200.0, &["O", "o", "."], Color::Srgba(WHITE));
+            (BombArea::Circle(200.0), 1)
+        }
+        SpellCard::HomingAmulet => {
+            spawn_homing_amulets(&mut commands, &font.0, player_pos);
+            spawn_bomb_ring(&mut commands, &font.0, player_pos, 300.0, &["=", "#"], Color::Srgba(RED_300));
+            (BombArea::Circle(300.0), 1)
+        }
+        SpellCard::MasterSpark => {
+            let half_width = spawn_master_spark(&mut commands, &font.0, player_pos, &window);
+            (BombArea::Column(half_width), 2)
+        }
+        SpellCard::StardustReverie => {
+            spawn_stardust(&mut commands, &font.0, player_pos);
+            spawn_bomb_ring(&mut commands, &font.0, player_pos, 320.0, &["*", "+", "."], Color::Srgba(YELLOW_300));
+            (BombArea::Circle(320.0), 1)
+        }
+    };
+
+    commands.spawn((
+        StateScoped(AppState::InGame),
+        ActiveBomb {
+            duration: Timer::from_seconds(BOMB_DURATION, TimerMode::Once),
+            damage_tick: Timer::from_seconds(0.1, TimerMode::Repeating),
+            area,
+            damage,
+        },
+    ));
+}
+
+fn spend_bomb(
+    mut events: EventReader<BombUsed>,
+    mut bombs: ResMut<PlayerBombs>,
+    mut bosses: Query<&mut Boss>,
+) {
+    for _ in events.read() {
+        bombs.0 -= 1;
+        for mut boss in bosses.iter_mut() {
+            boss.failed = true;
+        }
+    }
+}
+
+fn follow_player(
+    player_query: Query<&Transform, (With<Player>, Without<FollowPlayer>)>,
+    mut query: Query<(&FollowPlayer, &mut Transform)>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    for (follow, mut transform) in query.iter_mut() {
+        transform.translation = player_transform.translation + follow.0;
+    }
+}
+
+fn bomb_effect(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bombs: Query<(Entity, &mut ActiveBomb)>,
+    player_query: Query<&Transform, With<Player>>,
+    bullets: Query<(Entity, &BulletTarget)>,
+    mut enemies: Query<(Entity, &mut Health, &Transform, Option<&Invincible>, Has<Boss>), With<Enemy>>,
+    items: Query<Entity, (Or<(With<PowerItem>, With<PointItem>)>, Without<AutoCollect>)>,
+    score_values: Query<&ScoreValue>,
+    mut enemy_killed: EventWriter<EnemyKilled>,
+) {
+    for (bomb_entity, mut bomb) in bombs.iter_mut() {
+        bomb.duration.tick(time.delta());
+        bomb.damage_tick.tick(time.delta());
+
+        for (bullet_entity, target) in bullets.iter() {
+            if matches!(target, BulletTarget::Player) {
+                commands.entity(bullet_entity).despawn();
+            }
+        }
+
+        for item_entity in items.iter() {
+            commands.entity(item_entity).insert(AutoCollect);
+        }
+
+        if bomb.damage_tick.just_finished() {
+            if let Ok(player_transform) = player_query.get_single() {
+                let player_pos = player_transform.translation.truncate();
+                for (enemy_entity, mut health, transform, invincible, is_boss) in enemies.iter_mut() {
+                    if invincible.is_some() || health.0 <= 0 {
+                        continue;
+                    }
+                    let enemy_pos = transform.translation.truncate();
+                    let in_area = match bomb.area {
+                        BombArea::Circle(radius) => player_pos.distance(enemy_pos) <= radius,
+                        BombArea::Column(half_width) => {
+                            (enemy_pos.x - player_pos.x).abs() <= half_width && enemy_pos.y >= player_pos.y
+                        }
+                    };
+                    if !in_area {
+                        continue;
+                    }
+                    health.0 -= bomb.damage;
+                    if health.0 <= 0 && !is_boss {
+                        let score = score_values.get(enemy_entity).map(|value| value.0).unwrap_or(0);
+                        kill_enemy(&mut commands, &mut enemy_killed, enemy_entity, enemy_pos, score);
+                    }
+                }
+            }
+        }
+
+        if bomb.duration.finished() {
+            commands.entity(bomb_entity).despawn();
+        }
+    }
+}
+
+fn bomb_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut BombParticle, &mut TextColor)>,
+) {
+    for (entity, mut timer, mut color) in query.iter_mut() {
+        timer.0.tick(time.delta());
+
+        let progress = timer.0.elapsed_secs() / timer.0.duration().as_secs_f32();
+        color.0.set_alpha((1.0 - progress).clamp(0.0, 1.0));
+
+        if timer.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// bullets the graze zone can touch
+type GrazeBullets<'w, 's> = Query<'w, 's, (
+        Entity,
+        &'static mut Velocity,
+        Option<&'static GrazingBullet>,
+        &'static mut TextColor,
+        &'static Transform,
+    ),
+    With<BulletTarget>>;
+
+fn match_graze_bullet_pair(
+    e1: Entity,
+    e2: Entity,
+    graze_zone: &Query<(), With<GrazeZone>>,
+    bullets: &GrazeBullets,
+) -> Option<Entity> {
+    if graze_zone.get(e1).is_ok() && bullets.get(e2).is_ok() {
+        Some(e2)
+    } else if graze_zone.get(e2).is_ok() && bullets.get(e1).is_ok() {
+        Some(e1)
+    } else {
+        None
+    }
+}
+
+
+fn player_graze(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    graze_zone: Query<(), With<GrazeZone>>,
+    mut bullets: GrazeBullets,
+    mut grazed: EventWriter<Grazed>,
+) {
+    const GRAZE_DECAY: f32 = 0.7;
+    const BLOOM_BRIGHTNESS: f32 = 4.0;
+
+    for event in events.read() {
+        match event {
+            CollisionEvent::Started(e1, e2, _) => {
+                if let Some(bullet_entity) = match_graze_bullet_pair(*e1, *e2, &graze_zone, &bullets)
+                    && let Ok((entity, mut velocity, None, mut text_color, transform)) = bullets.get_mut(bullet_entity)
+                {
+                    velocity.linvel *= GRAZE_DECAY;
+
+                    let original = text_color.0;
+                    commands.entity(entity).insert(GrazingBullet {
+                        speed_decay: GRAZE_DECAY,
+                        original_color: original
+                    });
+
+                    text_color.0 = Color::from(original.to_linear() * BLOOM_BRIGHTNESS);
+                    grazed.send(Grazed {
+                        bullet: entity,
+                        position: transform.translation.truncate(),
+                    });
+                }
+            }
+            CollisionEvent::Stopped(e1, e2, _) => {
+                if let Some(bullet_entity) = match_graze_bullet_pair(*e1, *e2, &graze_zone, &bullets)
+                    && let Ok((entity, mut velocity, Some(graze), mut text_color, _)) = bullets.get_mut(bullet_entity)
+                {
+                    velocity.linvel /= graze.speed_decay;
+                    text_color.0 = graze.original_color;
+                    commands.entity(entity).remove::<GrazingBullet>();
+                }
+            }
+        }
+    }
+}
+
+fn shot_bullet_info(
+    shot: &ShotDef,
+    font: &Handle<Font>,
+) -> BulletInfo {
+    let bullet_type = match shot.kind {
+        ShotKind::Straight => BulletType::Normal,
+        ShotKind::Homing { rotate_speed } => BulletType::Homing(HomingBullet {
+            speed: shot.speed,
+            rotate_speed,
+        }),
+    };
+    BulletInfo {
+        bullet_type,
+        target: BulletTarget::Enemy,
+        text: Text2d::new(shot.glyph),
+        text_font: TextFont {
+            font: font.clone(),
+            font_size: 30.0,
+            ..default()
+        },
+        text_layout: Default::default(),
+        text_color: TextColor(shot.color),
+        collider: Collider::ball(5.0),
+    }
+}
+
+fn player_shoot(
+    mut query: Query<(&Transform, &mut ShootCooldown), With<Player>>,
+    font: Res<AsciiFont>,
+    mut commands: Commands,
+    powers: Res<PlayerPowers>,
+    character: Res<CharacterDef>,
+    mut player_shot: EventWriter<PlayerShot>,
+) {
+    for (transform, mut cooldown) in query.iter_mut() {
+        if cooldown.0.finished() {
+            const  BASE_DIRECTION: Vec2 = Vec2::Y;
+
+            let shot = &character.shot;
+            let tier = shot.tier(powers.0);
+            let bullet = shot_bullet_info(shot, &font.0);
+
+            for i in 0..tier.num_bullets {
+                let offset = i as f32 - (tier.num_bullets - 1) as f32 / 2.0;
+                let angle_rad = offset * tier.angle_step_deg.to_radians();
+                let rotated_direction = Vec2::from_angle(angle_rad).rotate(BASE_DIRECTION);
+                let position = transform.translation + Vec3::X * offset * tier.spacing;
+
+                let mut bullet_entity = commands.spawn((
+                    StateScoped(AppState::InGame),
+                    bullet.to_bundle(),
+                    Transform::from_translation(position),
+                    Velocity::linear(rotated_direction * shot.speed),
+                    BulletDamage(shot.damage),
+                ));
+                bullet.bullet_type.insert_into(&mut bullet_entity);
+            }
+            player_shot.send(PlayerShot {
+                position: transform.translation.truncate(),
+            });
+            cooldown.0.reset();
+        }
+    }
+}
+
+fn clamp_player_position(
+    mut query: Query<&mut Transform, With<Player>>,
+    window: Res<WindowSize>,
+) {
+    for mut transform in query.iter_mut() {
+        let pos = &mut transform.translation;
+        pos.x = pos.x.clamp(-window.width / 2.0 + 45.0, window.width / 2.0 * 0.25 - 5.0);
+        pos.y = pos.y.clamp(-window.height / 2.0 + 45.0, window.height / 2.0 - 45.0);
+    }
+}
+
+fn show_judge_point(
+    mut query: Query<&mut Visibility, With<JudgePoint>>,
+) {
+    for mut visibility in query.iter_mut() {
+        *visibility = Visibility::Visible;
+    }
+}
+fn hide_judge_point(
+    mut query: Query<&mut Visibility, With<JudgePoint>>,
+    config: Res<Config>,
+) {
+    if config.always_show_hitbox {
+        return;
+    }
+    for mut visibility in query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+fn apply_hitbox_option(
+    mut query: Query<&mut Visibility, With<JudgePoint>>,
+    config: Res<Config>,
+    input: Res<PlayerInput>,
+) {
+    let visible = config.always_show_hitbox || input.pressed(InputFrame::FOCUS);
+    for mut visibility in query.iter_mut() {
+        *visibility = if visible { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+fn support_unit_focus(
+    mut query: Query<(&SupportUnit, &mut Transform)>,
+    time: Res<Time>,
+) {
+    const FOCUS_SPEED: f32 = 10.0;
+    const POSITION_EPSILON: f32 = 0.5;
+    for (support, mut transform) in query.iter_mut() {
+        let target = support.focus_position;
+        let current = transform.translation;
+        if current.distance(target) < POSITION_EPSILON {
+            continue;
+        }
+        let new = current.lerp(target, FOCUS_SPEED * time.delta_secs());
+        transform.translation = new;
+    }
+}
+fn support_unit_reset(
+    mut query: Query<(&SupportUnit, &mut Transform)>,
+    time: Res<Time>,
+) {
+    const RESET_SPEED: f32 = 10.0;
+    const POSITION_EPSILON: f32 = 0.5;
+    for (support, mut transform) in query.iter_mut() {
+        let target = support.original_position;
+        let current = transform.translation;
+        if current.distance(target) < POSITION_EPSILON {
+            continue;
+        }
+        let new = current.lerp(target, RESET_SPEED * time.delta_secs());
+        transform.translation = new;
+    }
+}
+
+
+fn player_movement(
+    input: Res<PlayerInput>,
+    mut player_query: Query<&mut Velocity, With<Player>>,
+    character: Res<CharacterDef>,
+) {
+    for mut velocity in player_query.iter_mut() {
+        let mut direction = Vec2::ZERO;
+
+        if input.pressed(InputFrame::UP) {
+            direction.y += 1.0;
+        }
+        if input.pressed(InputFrame::DOWN) {
+            direction.y -= 1.0;
+        }
+        if input.pressed(InputFrame::LEFT) {
+            direction.x -= 1.0;
+        }
+        if input.pressed(InputFrame::RIGHT) {
+            direction.x += 1.0;
+        }
+
+        direction = direction.normalize_or_zero();
+
+        let speed = if input.pressed(InputFrame::FOCUS) {
+            character.focus_speed
+        } else {
+            character.speed
+        };
+
+        velocity.linvel = direction * speed;
+    }
+}
+
+fn pause_game(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut rapier_query: Query<&mut RapierConfiguration>,
+) {
+    next_state.set(GameState::Paused);
+    if let Ok(mut rapier) = rapier_query.get_single_mut() {
+        rapier.physics_pipeline_active = false;
+    }
+}
+
+fn clear_stage(
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    next_state.set(GameState::StageClear);
+}
+
+fn freeze_physics(
+    mut rapier_query: Query<&mut RapierConfiguration>,
+) {
+    if let Ok(mut rapier) = rapier_query.get_single_mut() {
+        rapier.physics_pipeline_active = false;
+    }
+}
+
+fn track_slowdown(
+    time: Res<Time<bevy::time::Real>>,
+    mut tracker: ResMut<SlowdownTracker>,
+) {
+    tracker.elapsed += time.delta_secs();
+}
+
+fn count_slowdown_tick(
+    mut tracker: ResMut<SlowdownTracker>,
+) {
+    tracker.ticks += 1;
+}
+
+fn high_score_entry(
+    score: &Score,
+    runner: &StageRunner,
+    slowdown: &SlowdownTracker,
+) -> HighScoreEntry {
+    HighScoreEntry {
+        name: String::new(),
+        score: score.0,
+        stage: runner.stage as u32 + 1,
+        date: today(),
+        slowdown: slowdown.rate(),
+    }
+}
+
+/// keep the best qualifying score of the run until the name entry screen picks it up
+fn queue_high_score(
+    commands: &mut Commands,
+    pending: Option<&PendingHighScore>,
+    high_scores: &HighScores,
+    difficulty: &PlayerDifficulty,
+    character: &PlayerCharacter,
+    entry: HighScoreEntry,
+) {
+    if !high_scores.qualifies(difficulty.0, character.0, entry.score) {
+        return;
+    }
+    if pending.is_some_and(|pending| pending.entry.score >= entry.score) {
+        return;
+    }
+    commands.insert_resource(PendingHighScore {
+        difficulty: difficulty.0,
+        character: character.0,
+        entry,
+    });
+}
+
+fn record_high_score(
+    mut commands: Commands,
+    score: Res<Score>,
+    runner: Res<StageRunner>,
+    slowdown: Res<SlowdownTracker>,
+    difficulty: Res<PlayerDifficulty>,
+    character: Res<PlayerCharacter>,
+    high_scores: Res<HighScores>,
+    pending: Option<Res<PendingHighScore>>,
+) {
+    let entry = high_score_entry(&score, &runner, &slowdown);
+    queue_high_score(&mut commands, pending.as_deref(), &high_scores, &difficulty, &character, entry);
+}
+
+fn continue_game(
+    mut events: EventReader<GameContinued>,
+    difficulty: Res<DifficultyDef>,
+    mut lives: ResMut<PlayerLives>,
+    mut bombs: ResMut<PlayerBombs>,
+    mut score: ResMut<Score>,
+    mut continues: ResMut<PlayerContinues>,
+    mut commands: Commands,
+    runner: Res<StageRunner>,
+    slowdown: Res<SlowdownTracker>,
+    player_difficulty: Res<PlayerDifficulty>,
+    character: Res<PlayerCharacter>,
+    high_scores: Res<HighScores>,
+    pending: Option<Res<PendingHighScore>>,
+    practice: Option<Res<PracticeRun>>,
+) {
+    for _ in events.read() {
+        // the score so far still counts, the continued run starts over from zero
+        if practice.is_none() {
+            let entry = high_score_entry(&score, &runner, &slowdown);
+            queue_high_score(&mut commands, pending.as_deref(), &high_scores, &player_difficulty, &character, entry);
+        }
+        lives.0 = difficulty.lives;
+        bombs.0 = difficulty.bombs;
+        score.0 = 0;
+        continues.0 += 1;
+    }
+}
+
+fn resume_game(
+    mut rapier_query: Query<&mut RapierConfiguration>,
+) {
+    if let Ok(mut rapier) = rapier_query.get_single_mut() {
+        rapier.physics_pipeline_active = true;
+    }
+}
+
+
+fn setup_game(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+    player_character: Res<PlayerCharacter>,
+    player_difficulty: Res<PlayerDifficulty>,
+    high_scores: Res<HighScores>,
+    tick_rate: Res<TickRate>,
+    config: Res<Config>,
+    practice: Option<Res<PracticeRun>>,
+) {
+    let character = player_character.0.def();
+    let difficulty = player_difficulty.0.def();
+    match practice.as_deref() {
+        Some(practice) => {
+            commands.insert_resource(match practice.target {
+                PracticeTarget::Stage(stage) => StageRunner::new(stage),
+                PracticeTarget::SpellCard { stage, boss, phase } => StageRunner::spell_practice(stage, boss, phase),
+            });
+            commands.insert_resource(PlayerLives(practice.lives));
+            commands.insert_resource(PlayerBombs(practice.bombs));
+            commands.insert_resource(PlayerPowers(practice.power));
+        }
+        None => {
+            commands.insert_resource(StageRunner {
+                extra: player_difficulty.0 == Difficulty::Extra,
+                ..StageRunner::new(0)
+            });
+            commands.insert_resource(PlayerLives(difficulty.lives));
+            commands.insert_resource(PlayerBombs(difficulty.bombs));
+            commands.insert_resource(PlayerPowers(0));
+        }
+    }
+    commands.insert_resource(PlayerGraze(0));
+    commands.insert_resource(PlayerPoints(0));
+    commands.insert_resource(PlayerContinues(0));
+    commands.insert_resource(Score(0));
+    commands.insert_resource(HiScore(high_scores.best(player_difficulty.0, player_character.0)));
+    commands.insert_resource(SlowdownTracker::new(&tick_rate));
+
+    let font_size = 40.0;
+    let text_font = TextFont {
+        font: font.0.clone(),
+        font_size: font_size.clone(),
+        ..default()
+    };
+
+    commands.spawn((
+        StateScoped(AppState::InGame),
+        Text2d::new(character.glyph),
+        text_font.clone(),
+        TextLayout::default(),
+        TextColor(character.color),
+
+        Player,
+        ShootCooldown(Timer::from_seconds(character.shot.cooldown,  TimerMode::Once)),
+
+        RigidBody::Dynamic,
+        Sensor,
+        GravityScale(0.0),
+        Collider::ball(character.hitbox_radius),
+        Velocity::zero(),
+        Transform::from_translation(PLAYER_RESPAWN_POS),
+
+        ActiveEvents::COLLISION_EVENTS,
+        CollisionGroups::new(Group::GROUP_1, Group::GROUP_4 | Group::GROUP_6 | Group::GROUP_8)
+    )).with_children(|builder| {
+        builder.spawn((
+            JudgePoint,
+            Text2d::new("·"),
+            TextFont {
+                font: font.0.clone(),
+                font_size: 60.0,
+                ..default()
+            },
+            TextLayout::default(),
+            TextColor(Color::Srgba(WHITE)),
+            if config.always_show_hitbox { Visibility::Visible } else { Visibility::Hidden },
+            Transform::from_translation(Vec3::new(0.0, 5.0, 1.0)),
+        ));
+        builder.spawn((
+            GrazeZone,
+            Collider::ball(character.graze_radius),
+            CollisionGroups::new(Group::GROUP_7, Group::GROUP_8),
+            ActiveEvents::COLLISION_EVENTS,
+            Sensor,
+        ));
+    });
+
+    commands.insert_resource(character);
+    commands.insert_resource(difficulty);
+}
+
+fn setup_game_resources(
+    mut commands: Commands,
+    tick_rate: Res<TickRate>,
+) {
+    commands.insert_resource(StageRunner::new(0));
+    commands.insert_resource(PlayerLives(2));
+    commands.insert_resource(PlayerBombs(3));
+    commands.insert_resource(PlayerPowers(0));
+    commands.insert_resource(PlayerGraze(0));
+    commands.insert_resource(PlayerPoints(0));
+    commands.insert_resource(PlayerContinues(0));
+    commands.insert_resource(Score(0));
+    commands.insert_resource(HiScore(0));
+    commands.insert_resource(SlowdownTracker::new(&tick_rate));
+
+    commands.insert_resource(ShowColliderDebug(false));
+    commands.insert_resource(PlayerSpellCard(SpellCard::default()));
+    commands.insert_resource(PlayerCharacter(Character::default()));
+    commands.insert_resource(PlayerDifficulty(Difficulty::default()));
+
+    commands.insert_resource(WindowSize {
+        width: 1280.0,
+        height: 720.0,
+    });
+}
+
+/// the whole simulation without windowing, rendering or audio, expects an `AsciiFont` resource
+pub struct GamePlugin;
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(TickRatePlugin)
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule())
+            .add_plugins((
+                GameEventPlugin,
+                SavePlugin,
+                ConfigPlugin,
+                RngPlugin,
+                HighScorePlugin,
+                PlayerInputPlugin,
+                ReplayPlugin,
+                StagePlugin,
+                PracticePlugin,
+                ExtraPlugin,
+                DialoguePlugin,
+            ))
+            .init_state::<AppState>()
+            .add_sub_state::<GameState>()
+            .enable_state_scoped_entities::<AppState>()
+            .enable_state_scoped_entities::<GameState>()
+            .add_event::<GameContinued>()
+            .add_systems(Startup, setup_game_resources)
+            .add_systems(OnEnter(AppState::InGame), setup_game)
+            .add_systems(Update, pause_game.run_if(in_state(GameState::Running).and(input_just_pressed(Action::Pause))))
+            .add_systems(OnExit(GameState::Paused), resume_game)
+            // practice and replays never reach the score tables
+            .add_systems(OnExit(AppState::InGame), record_high_score
+                .run_if(not(resource_exists::<ReplayPlayback>).and(not(resource_exists::<PracticeRun>))))
+            .add_systems(Update, track_slowdown.run_if(in_state(GameState::Running)))
+            .add_systems(OnEnter(GameState::GameOver), freeze_physics)
+            .add_systems(OnExit(GameState::GameOver), resume_game)
+            .add_systems(OnEnter(GameState::Dialogue), freeze_physics)
+            .add_systems(OnExit(GameState::Dialogue), resume_game)
+            // practice has its own way back to the menu, and a replay stops where its recording did
+            // an after-boss dialogue cued on the last tick is read before the clear screen
+            .add_systems(Update, clear_stage.run_if(
+                in_state(GameState::Running)
+                    .and(|runner: Option<Res<StageRunner>>| runner.is_some_and(|runner| runner.finished))
+                    .and(not(on_event::<DialogueCue>))
+                    .and(not(resource_exists::<PracticeRun>))
+                    .and(not(resource_exists::<ReplayPlayback>))
+            ))
+            .add_systems(OnEnter(GameState::StageClear), freeze_physics)
+            .add_systems(Update, continue_game.run_if(on_event::<GameContinued>))
+            .add_systems(Update, (
+                update_lives_text.run_if(resource_changed::<PlayerLives>),
+                update_bombs_text.run_if(resource_changed::<PlayerBombs>),
+                update_powers_text.run_if(resource_changed::<PlayerPowers>),
+                update_graze_text.run_if(resource_changed::<PlayerGraze>),
+                update_points_text.run_if(resource_changed::<PlayerPoints>),
+                update_score_text.run_if(resource_changed::<Score>),
+                update_boss_ui,
+            ).run_if(in_state(GameState::Running)))
+            .add_systems(Update, update_debug_overlay.run_if(in_state(AppState::InGame)))
+            .add_systems(Update, apply_hitbox_option.run_if(in_state(AppState::InGame).and(resource_changed::<Config>)))
+            // everything that changes the game state runs in a fixed order once per tick
+            .add_systems(FixedUpdate, (
+                (
+                    run_stage,
+                    boss_phase,
+                    boss_movement,
+                    player_movement,
+                    player_shoot.run_if(player_input_pressed(InputFrame::SHOOT)),
+                    player_bomb.run_if(player_input_just_pressed(InputFrame::BOMB)),
+                    show_judge_point.run_if(player_input_just_pressed(InputFrame::FOCUS)),
+                    hide_judge_point.run_if(player_input_just_released(InputFrame::FOCUS)),
+                    support_unit_focus.run_if(player_input_pressed(InputFrame::FOCUS)),
+                    support_unit_reset.run_if(not(player_input_pressed(InputFrame::FOCUS))),
+                    (
+                        spawn_support_units,
+                        despawn_support_units,
+                    ).run_if(resource_changed::<PlayerPowers>),
+                    single_shoot,
+                    fan_shoot,
+                    laser_bullet,
+                    linear_movement,
+                    homing_bullet,
+                    spiral_bullet,
+                    item_gravity,
+                    attract_items,
+                ).chain(),
+                (
+                    bomb_effect,
+                    follow_player,
+                    tick_invincibility,
+                    tick_cooldown_timer,
+                    enemy_hit_particles,
+                    enemy_death_particles,
+                    player_death_particles,
+                    bomb_particles,
+                    despawn_bullets,
+                    despawn_items,
+                    despawn_enemies,
+                    clamp_player_position,
+                    count_slowdown_tick,
+                ).chain(),
+            ).chain().before(PhysicsSet::SyncBackend).run_if(in_state(GameState::Running)))
+            .add_systems(FixedUpdate, (
+                (
+                    player_graze,
+                    bullet_hit,
+                    item_hit,
+                ).chain().run_if(on_event::<CollisionEvent>),
+                // outcomes of this tick, fired above or by bombs before the physics step
+                (
+                    spawn_enemy_hit_particle.run_if(on_event::<EnemyHit>),
+                    (
+                        score_enemy_kills,
+                        drop_enemy_items,
+                        spawn_enemy_killed_particles,
+                    ).chain().run_if(on_event::<EnemyKilled>),
+                    (
+                        lose_life,
+                        drop_player_power,
+                        spawn_player_hit_particles,
+                    ).chain().run_if(on_event::<PlayerHit>),
+                    score_graze.run_if(on_event::<Grazed>),
+                    collect_item.run_if(on_event::<ItemCollected>),
+                    spend_bomb.run_if(on_event::<BombUsed>),
+                ).chain(),
+            ).chain().after(PhysicsSet::Writeback));
+    }
+}
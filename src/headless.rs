@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use bevy::asset::AssetPlugin;
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use crate::game::GamePlugin;
+use crate::resource::{AsciiBoldFont, AsciiFont};
+use crate::rng::SeedOverride;
+use crate::save::SaveDir;
+use crate::stage::StageList;
+use crate::state::AppState;
+
+/// save directory of a single headless app, removed again when the app is dropped
+#[derive(Resource)]
+struct ScratchSaveDir(PathBuf);
+impl ScratchSaveDir {
+    fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "asciihou-headless-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed),
+        ));
+        // a leftover from an earlier process with the same pid
+        let _ = std::fs::remove_dir_all(&dir);
+        ScratchSaveDir(dir)
+    }
+}
+impl Drop for ScratchSaveDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// `GamePlugin` on top of `MinimalPlugins` with placeholder fonts, every update advances time by one fixed tick
+pub struct HeadlessPlugin;
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        // keep test runs away from the player's files and from each other
+        if !app.world().contains_resource::<SaveDir>() {
+            let scratch = ScratchSaveDir::new();
+            app.insert_resource(SaveDir(scratch.0.clone()));
+            app.insert_resource(scratch);
+        }
+        app
+            .add_plugins((
+                MinimalPlugins,
+                StatesPlugin,
+                AssetPlugin::default(),
+                InputPlugin,
+                TransformPlugin,
+                HierarchyPlugin,
+            ))
+            .insert_resource(AsciiFont(Handle::default()))
+            .insert_resource(AsciiBoldFont(Handle::default()))
+            .add_plugins(GamePlugin);
+
+        let timestep = app.world().resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+    }
+}
+
+/// a headless app past startup with the stages and their dialogues loaded, still outside of a run
+pub fn headless_app(seed: u64) -> App {
+    const MAX_LOAD_UPDATES: usize = 10_000;
+
+    let mut app = App::new();
+    app
+        .insert_resource(SeedOverride(Some(seed)))
+        .add_plugins(HeadlessPlugin);
+
+    for _ in 0..MAX_LOAD_UPDATES {
+        app.update();
+        let world = app.world();
+        // the dialogues of a stage are loaded as its dependencies
+        let loaded = world.get_resource::<StageList>().is_some_and(|stages| {
+            let asset_server = world.resource::<AssetServer>();
+            stages.iter().all(|handle| asset_server.is_loaded_with_dependencies(handle))
+        });
+        if loaded {
+            return app;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    panic!("stages did not finish loading");
+}
+
+/// enter `AppState::InGame` with whatever character, difficulty and replay resources are set
+pub fn start_run(app: &mut App) {
+    app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::InGame);
+    app.update();
+}
+
+/// run updates until exactly `ticks` more fixed ticks have been simulated
+pub fn step_ticks(app: &mut App, ticks: u32) {
+    let fixed_time = app.world().resource::<Time<Fixed>>();
+    let target = fixed_time.elapsed() + fixed_time.timestep() * ticks;
+    while app.world().resource::<Time<Fixed>>().elapsed() < target {
+        app.update();
+    }
+}
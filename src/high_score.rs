@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::character::Character;
+use crate::difficulty::Difficulty;
+use crate::save::SaveDir;
+
+/// entries kept per difficulty × character table
+pub const HIGH_SCORE_ENTRIES: usize = 10;
+const HIGH_SCORE_FILE: &str = "score.ron";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: u64,
+    /// last stage reached, starting from 1
+    pub stage: u32,
+    pub date: String,
+    /// percentage of frames lost to slowdown
+    pub slowdown: f32,
+}
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScoreTable {
+    pub difficulty: Difficulty,
+    pub character: Character,
+    pub entries: Vec<HighScoreEntry>,
+}
+/// a qualifying run waiting for the player to enter a name
+#[derive(Resource, Clone)]
+pub struct PendingHighScore {
+    pub difficulty: Difficulty,
+    pub character: Character,
+    pub entry: HighScoreEntry,
+}
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct HighScores {
+    pub tables: Vec<HighScoreTable>,
+}
+impl HighScores {
+    pub fn table(&self, difficulty: Difficulty, character: Character) -> &[HighScoreEntry] {
+        self.tables
+            .iter()
+            .find(|table| table.difficulty == difficulty && table.character == character)
+            .map(|table| table.entries.as_slice())
+            .unwrap_or(&[])
+    }
+    pub fn best(&self, difficulty: Difficulty, character: Character) -> u64 {
+        self.table(difficulty, character).first().map(|entry| entry.score).unwrap_or(0)
+    }
+    pub fn qualifies(&self, difficulty: Difficulty, character: Character, score: u64) -> bool {
+        let table = self.table(difficulty, character);
+        score > 0 && (table.len() < HIGH_SCORE_ENTRIES || table.iter().any(|entry| score > entry.score))
+    }
+    /// insert the entry if it makes the top N, returning its rank
+    pub fn insert(&mut self, difficulty: Difficulty, character: Character, entry: HighScoreEntry) -> Option<usize> {
+        if !self.qualifies(difficulty, character, entry.score) {
+            return None;
+        }
+        let index = match self.tables
+            .iter()
+            .position(|table| table.difficulty == difficulty && table.character == character)
+        {
+            Some(index) => index,
+            None => {
+                self.tables.push(HighScoreTable { difficulty, character, entries: Vec::new() });
+                self.tables.len() - 1
+            }
+        };
+        let entries = &mut self.tables[index].entries;
+        let rank = entries.iter().position(|other| entry.score > other.score).unwrap_or(entries.len());
+        entries.insert(rank, entry);
+        entries.truncate(HIGH_SCORE_ENTRIES);
+        Some(rank)
+    }
+    pub fn load(dir: &SaveDir) -> Self {
+        dir.load(HIGH_SCORE_FILE).unwrap_or_default()
+    }
+    pub fn save(&self, dir: &SaveDir) {
+        dir.save(HIGH_SCORE_FILE, self);
+    }
+}
+
+fn load_high_scores(
+    mut commands: Commands,
+    save_dir: Res<SaveDir>,
+) {
+    commands.insert_resource(HighScores::load(&save_dir));
+}
+
+pub struct HighScorePlugin;
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_high_scores);
+    }
+}
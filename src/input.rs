@@ -0,0 +1,351 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::replay::{ReplayPlayback, ReplayRecorder};
+use crate::save::SaveDir;
+use crate::state::{AppState, GameState};
+
+const KEY_BINDINGS_FILE: &str = "keys.ron";
+
+/// everything the player can press, read through `ButtonInput<Action>` instead of raw key codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Shoot,
+    Focus,
+    Bomb,
+    Pause,
+    Confirm,
+    Back,
+    /// ends a dialogue at once, fast-forwards a replay while held
+    Skip,
+    /// toggles the collider debug view
+    Debug,
+    /// leaves the game from the title screen
+    Quit,
+}
+impl Action {
+    pub const ALL: [Action; 13] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Shoot,
+        Action::Focus,
+        Action::Bomb,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::Skip,
+        Action::Debug,
+        Action::Quit,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Shoot => "Shoot",
+            Action::Focus => "Focus",
+            Action::Bomb => "Bomb",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::Skip => "Skip",
+            Action::Debug => "Debug View",
+            Action::Quit => "Quit",
+        }
+    }
+    fn in_game(&self) -> bool {
+        !matches!(self, Action::Confirm | Action::Back | Action::Quit)
+    }
+    fn in_menu(&self) -> bool {
+        matches!(self, Action::Up | Action::Down | Action::Left | Action::Right | Action::Confirm | Action::Back | Action::Quit)
+    }
+    /// two actions read on the same screen cannot share a key, shoot and confirm can
+    pub fn conflicts_with(&self, other: Action) -> bool {
+        *self != other && ((self.in_game() && other.in_game()) || (self.in_menu() && other.in_menu()))
+    }
+}
+
+/// keys bound to each action, the first key is the one the key config screen rebinds
+#[derive(Resource, Serialize, Deserialize, Clone)]
+pub struct KeyBindings(pub HashMap<Action, Vec<KeyCode>>);
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings(HashMap::from_iter([
+            (Action::Up, vec![KeyCode::ArrowUp]),
+            (Action::Down, vec![KeyCode::ArrowDown]),
+            (Action::Left, vec![KeyCode::ArrowLeft]),
+            (Action::Right, vec![KeyCode::ArrowRight]),
+            (Action::Shoot, vec![KeyCode::KeyZ]),
+            (Action::Focus, vec![KeyCode::ShiftLeft]),
+            (Action::Bomb, vec![KeyCode::KeyX]),
+            (Action::Pause, vec![KeyCode::Escape]),
+            (Action::Confirm, vec![KeyCode::KeyZ, KeyCode::Enter]),
+            (Action::Back, vec![KeyCode::KeyX, KeyCode::Escape]),
+            (Action::Skip, vec![KeyCode::ControlLeft, KeyCode::ControlRight]),
+            (Action::Debug, vec![KeyCode::F3]),
+            (Action::Quit, vec![KeyCode::KeyQ]),
+        ]))
+    }
+}
+impl KeyBindings {
+    pub fn load(dir: &SaveDir) -> Self {
+        let mut bindings: KeyBindings = dir.load(KEY_BINDINGS_FILE).unwrap_or_default();
+        // actions added after the file was written keep their default keys
+        for (action, keys) in KeyBindings::default().0 {
+            bindings.0.entry(action).or_insert(keys);
+        }
+        bindings
+    }
+    pub fn save(&self, dir: &SaveDir) {
+        dir.save(KEY_BINDINGS_FILE, self);
+    }
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// make `key` the main key of `action`, every conflicting action loses it and gets the old main key if it has none left
+    /// returns the actions that gave up the key
+    pub fn rebind(&mut self, action: Action, key: KeyCode) -> Vec<Action> {
+        let old_key = self.keys(action).first().copied();
+        let conflicts: Vec<Action> = Action::ALL
+            .into_iter()
+            .filter(|other| action.conflicts_with(*other) && self.keys(*other).contains(&key))
+            .collect();
+
+        let keys = self.0.entry(action).or_default();
+        keys.retain(|bound| *bound != key);
+        match keys.first_mut() {
+            Some(first) => *first = key,
+            None => keys.push(key),
+        }
+
+        for other in conflicts.iter().copied() {
+            let keys = self.0.entry(other).or_default();
+            keys.retain(|bound| *bound != key);
+            if !keys.is_empty() {
+                continue;
+            }
+            // the old key must not end up on two actions that clash with each other
+            if let Some(old_key) = old_key
+                && !Action::ALL.into_iter().any(|holder| other.conflicts_with(holder) && self.keys(holder).contains(&old_key))
+            {
+                self.0.entry(other).or_default().push(old_key);
+            }
+        }
+        conflicts
+    }
+}
+
+/// fixed controller layout, every connected gamepad drives the same actions
+const GAMEPAD_BINDINGS: [(Action, &[GamepadButton]); 11] = [
+    (Action::Up, &[GamepadButton::DPadUp]),
+    (Action::Down, &[GamepadButton::DPadDown]),
+    (Action::Left, &[GamepadButton::DPadLeft]),
+    (Action::Right, &[GamepadButton::DPadRight]),
+    (Action::Shoot, &[GamepadButton::South]),
+    (Action::Focus, &[GamepadButton::LeftTrigger, GamepadButton::RightTrigger]),
+    (Action::Bomb, &[GamepadButton::East]),
+    (Action::Pause, &[GamepadButton::Start]),
+    (Action::Confirm, &[GamepadButton::South]),
+    (Action::Back, &[GamepadButton::East]),
+    (Action::Skip, &[GamepadButton::Select]),
+];
+
+/// controller buttons that drive `action`
+pub fn gamepad_buttons(action: Action) -> &'static [GamepadButton] {
+    GAMEPAD_BINDINGS
+        .iter()
+        .find(|(bound, _)| *bound == action)
+        .map_or(&[], |(_, buttons)| buttons)
+}
+
+/// direction actions held by a stick, nothing inside the dead zone
+/// with `snap` the stick angle is rounded to the nearest of 8 directions, otherwise each axis is read on its own
+pub fn stick_directions(stick: Vec2, dead_zone: f32, snap: bool) -> Vec<Action> {
+    if stick.length() <= dead_zone {
+        return Vec::new();
+    }
+    let direction = if snap {
+        let octant = (stick.to_angle() / std::f32::consts::FRAC_PI_4).round();
+        let snapped = Vec2::from_angle(octant * std::f32::consts::FRAC_PI_4);
+        // drop the rounding noise of cos/sin so straight directions stay on one axis
+        snapped.round()
+    } else {
+        Vec2::new(
+            if stick.x.abs() > dead_zone { stick.x.signum() } else { 0.0 },
+            if stick.y.abs() > dead_zone { stick.y.signum() } else { 0.0 },
+        )
+    };
+
+    let mut actions = Vec::new();
+    if direction.y > 0.0 {
+        actions.push(Action::Up);
+    }
+    if direction.y < 0.0 {
+        actions.push(Action::Down);
+    }
+    if direction.x < 0.0 {
+        actions.push(Action::Left);
+    }
+    if direction.x > 0.0 {
+        actions.push(Action::Right);
+    }
+    actions
+}
+
+/// short key label for menus, `KeyZ` becomes `Z`
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+/// the gameplay buttons held during one tick, packed into bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct InputFrame(pub u8);
+impl InputFrame {
+    pub const UP: u8 = 1 << 0;
+    pub const DOWN: u8 = 1 << 1;
+    pub const LEFT: u8 = 1 << 2;
+    pub const RIGHT: u8 = 1 << 3;
+    pub const FOCUS: u8 = 1 << 4;
+    pub const SHOOT: u8 = 1 << 5;
+    pub const BOMB: u8 = 1 << 6;
+
+    pub fn pressed(&self, button: u8) -> bool {
+        self.0 & button != 0
+    }
+}
+
+/// what gameplay systems read instead of `ButtonInput<Action>`, sampled once per fixed tick from the actions or a replay
+#[derive(Resource, Default)]
+pub struct PlayerInput {
+    pub current: InputFrame,
+    pub previous: InputFrame,
+}
+impl PlayerInput {
+    pub fn pressed(&self, button: u8) -> bool {
+        self.current.pressed(button)
+    }
+    pub fn just_pressed(&self, button: u8) -> bool {
+        self.current.pressed(button) && !self.previous.pressed(button)
+    }
+    pub fn just_released(&self, button: u8) -> bool {
+        !self.current.pressed(button) && self.previous.pressed(button)
+    }
+}
+
+pub fn action_input_frame(actions: &ButtonInput<Action>) -> InputFrame {
+    let buttons = [
+        (Action::Up, InputFrame::UP),
+        (Action::Down, InputFrame::DOWN),
+        (Action::Left, InputFrame::LEFT),
+        (Action::Right, InputFrame::RIGHT),
+        (Action::Focus, InputFrame::FOCUS),
+        (Action::Shoot, InputFrame::SHOOT),
+        (Action::Bomb, InputFrame::BOMB),
+    ];
+    let mut frame = InputFrame::default();
+    for (action, button) in buttons {
+        if actions.pressed(action) {
+            frame.0 |= button;
+        }
+    }
+    frame
+}
+
+fn load_key_bindings(
+    mut commands: Commands,
+    save_dir: Res<SaveDir>,
+) {
+    commands.insert_resource(KeyBindings::load(&save_dir));
+}
+
+fn update_actions(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    bindings: Res<KeyBindings>,
+    config: Res<Config>,
+    mut actions: ResMut<ButtonInput<Action>>,
+) {
+    let mut gamepad_actions = Vec::new();
+    for gamepad in gamepads.iter() {
+        for (action, buttons) in GAMEPAD_BINDINGS {
+            if gamepad.any_pressed(buttons.iter().copied()) {
+                gamepad_actions.push(action);
+            }
+        }
+        gamepad_actions.extend(stick_directions(gamepad.left_stick(), config.stick_dead_zone, config.stick_snap));
+    }
+
+    actions.clear();
+    for action in Action::ALL {
+        let pressed = keyboard_input.any_pressed(bindings.keys(action).iter().copied())
+            || gamepad_actions.contains(&action);
+        if pressed && !actions.pressed(action) {
+            actions.press(action);
+        } else if !pressed && actions.pressed(action) {
+            actions.release(action);
+        }
+    }
+}
+
+pub fn player_input_pressed(button: u8) -> impl FnMut(Res<PlayerInput>) -> bool + Clone {
+    move |input: Res<PlayerInput>| input.pressed(button)
+}
+pub fn player_input_just_pressed(button: u8) -> impl FnMut(Res<PlayerInput>) -> bool + Clone {
+    move |input: Res<PlayerInput>| input.just_pressed(button)
+}
+pub fn player_input_just_released(button: u8) -> impl FnMut(Res<PlayerInput>) -> bool + Clone {
+    move |input: Res<PlayerInput>| input.just_released(button)
+}
+
+fn reset_player_input(
+    mut input: ResMut<PlayerInput>,
+) {
+    *input = PlayerInput::default();
+}
+
+fn sample_player_input(
+    actions: Res<ButtonInput<Action>>,
+    mut input: ResMut<PlayerInput>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+) {
+    let frame = match playback {
+        Some(mut playback) => playback.next_frame().unwrap_or_default(),
+        None => action_input_frame(&actions),
+    };
+    input.previous = input.current;
+    input.current = frame;
+
+    if let Some(mut recorder) = recorder {
+        recorder.record(frame);
+    }
+}
+
+pub struct PlayerInputPlugin;
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PlayerInput>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<ButtonInput<Action>>()
+            .add_systems(Startup, load_key_bindings)
+            .add_systems(PreUpdate, update_actions.after(InputSystem))
+            .add_systems(OnEnter(AppState::InGame), reset_player_input)
+            .add_systems(FixedPreUpdate, sample_player_input.run_if(in_state(GameState::Running)));
+    }
+}
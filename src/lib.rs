@@ -1,4 +1,22 @@
 pub mod ascii_animation;
 pub mod ui;
 pub mod state;
-pub mod resource;
\ No newline at end of file
+pub mod resource;
+pub mod stage;
+pub mod character;
+pub mod difficulty;
+pub mod save;
+pub mod high_score;
+pub mod rng;
+pub mod input;
+pub mod replay;
+pub mod tick;
+pub mod game;
+pub mod headless;
+pub mod event;
+pub mod sfx;
+pub mod music;
+pub mod config;
+pub mod practice;
+pub mod extra;
+pub mod dialogue;
\ No newline at end of file
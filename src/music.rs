@@ -0,0 +1,465 @@
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::asset::io::Reader;
+use bevy::audio::{AddAudioSource, Decodable, Source, Volume};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ascii_animation::CustomAssetLoaderError;
+use crate::dialogue::DialogueMusic;
+use crate::game::StageRunner;
+use crate::save::SaveDir;
+use crate::stage::{StageAsset, StageList};
+use crate::state::{AppState, GameState};
+
+const MUSIC_FILE: &str = "music.ron";
+
+/// a bgm file with its loop points, played from the start and then looping between `loop_start` and `loop_end` forever
+#[derive(Asset, TypePath, Clone)]
+pub struct MusicTrack {
+    pub audio: AudioSource,
+    pub loop_start: u64,
+    pub loop_end: Option<u64>,
+}
+impl Decodable for MusicTrack {
+    type DecoderItem = <AudioSource as Decodable>::DecoderItem;
+    type Decoder = LoopDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        let decoder = self.audio.decoder();
+        // loop points count frames, the decoder yields interleaved samples
+        let channels = decoder.channels() as u64;
+        LoopDecoder {
+            decoder,
+            position: 0,
+            loop_start: self.loop_start * channels,
+            loop_end: self.loop_end.map(|end| end * channels),
+            loop_samples: Vec::new(),
+            replay: None,
+        }
+    }
+}
+
+/// decodes the file once, keeping the loop body to play it again from memory
+pub struct LoopDecoder {
+    decoder: <AudioSource as Decodable>::Decoder,
+    /// interleaved samples since the start of the file
+    position: u64,
+    loop_start: u64,
+    loop_end: Option<u64>,
+    /// samples between the loop points, filled during the first pass
+    loop_samples: Vec<<AudioSource as Decodable>::DecoderItem>,
+    /// index into `loop_samples` once the first pass reached the loop end
+    replay: Option<usize>,
+}
+impl LoopDecoder {
+    fn next_replayed(&mut self) -> Option<<AudioSource as Decodable>::DecoderItem> {
+        let index = self.replay.unwrap_or(0);
+        // nothing after the loop start, stop instead of spinning
+        let sample = *self.loop_samples.get(index)?;
+        self.replay = Some((index + 1) % self.loop_samples.len());
+        Some(sample)
+    }
+}
+impl Iterator for LoopDecoder {
+    type Item = <AudioSource as Decodable>::DecoderItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.replay.is_some() || self.loop_end.is_some_and(|end| self.position >= end) {
+            return self.next_replayed();
+        }
+        let Some(sample) = self.decoder.next() else {
+            return self.next_replayed();
+        };
+        if self.position >= self.loop_start {
+            self.loop_samples.push(sample);
+        }
+        self.position += 1;
+        Some(sample)
+    }
+}
+impl Source for LoopDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+    fn channels(&self) -> u16 {
+        self.decoder.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.decoder.sample_rate()
+    }
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct MusicLibraryLoader;
+impl AssetLoader for MusicLibraryLoader {
+    type Asset = MusicLibraryAsset;
+    type Settings = ();
+    type Error = CustomAssetLoaderError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut library = ron::de::from_bytes::<MusicLibraryAsset>(&bytes)?;
+        for track in library.tracks.iter_mut() {
+            // a missing file only silences its own track
+            match load_context.read_asset_bytes(track.path.clone()).await {
+                Ok(bytes) => {
+                    track.source = Some(load_context.add_labeled_asset(track.id.clone(), MusicTrack {
+                        audio: AudioSource { bytes: bytes.into() },
+                        loop_start: track.loop_start,
+                        loop_end: track.loop_end,
+                    }));
+                }
+                Err(err) => warn!("Failed to read music track {}: {}", track.path, err),
+            }
+        }
+        Ok(library)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bgm.ron"]
+    }
+}
+
+#[derive(Asset, Deserialize, Serialize, TypePath)]
+pub struct MusicLibraryAsset {
+    /// track id played on the title screen and its menus
+    pub menu: String,
+    pub tracks: Vec<TrackDef>,
+}
+#[derive(Deserialize, Serialize)]
+pub struct TrackDef {
+    /// referenced by `menu` and by the `bgm` of stages and bosses
+    pub id: String,
+    pub path: String,
+    pub title: String,
+    /// shown under the track in the music room
+    #[serde(default)]
+    pub comment: String,
+    /// frame the loop jumps back to
+    #[serde(default)]
+    pub loop_start: u64,
+    /// frame the loop jumps back from, the end of the file if unset
+    #[serde(default)]
+    pub loop_end: Option<u64>,
+    /// none if the file could not be read
+    #[serde(skip)]
+    pub source: Option<Handle<MusicTrack>>,
+}
+impl MusicLibraryAsset {
+    pub fn track(&self, id: &str) -> Option<&TrackDef> {
+        self.tracks.iter().find(|track| track.id == id)
+    }
+}
+
+#[derive(Resource)]
+pub struct MusicLibrary(pub Handle<MusicLibraryAsset>);
+
+/// bgm channel volume
+#[derive(Resource)]
+pub struct BgmVolume(pub f32);
+impl Default for BgmVolume {
+    fn default() -> Self {
+        BgmVolume(1.0)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct MusicManager {
+    /// id of the track that should be playing
+    pub current: Option<String>,
+    /// picked in the music room, replaces the menu track
+    pub requested: Option<String>,
+    /// seconds the current track has been playing, not counting pauses
+    pub elapsed: f32,
+    player: Option<Entity>,
+}
+
+/// ids of every track the player has listened to, these are unlocked in the music room
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct HeardTracks(pub Vec<String>);
+impl HeardTracks {
+    pub fn load(dir: &SaveDir) -> Self {
+        dir.load(MUSIC_FILE).unwrap_or_default()
+    }
+    pub fn save(&self, dir: &SaveDir) {
+        dir.save(MUSIC_FILE, self);
+    }
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.iter().any(|heard| heard == id)
+    }
+}
+
+/// a track decoded to mono samples for the spectrum visualizer
+pub struct DecodedTrack {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub loop_start: u64,
+    pub loop_end: Option<u64>,
+}
+impl DecodedTrack {
+    pub fn decode(track: &MusicTrack) -> Self {
+        let decoder = track.audio.decoder();
+        let channels = decoder.channels().max(1) as usize;
+        let sample_rate = decoder.sample_rate();
+        let interleaved: Vec<i16> = decoder.collect();
+        let samples = interleaved
+            .chunks(channels)
+            .map(|frame| frame.iter().map(|sample| *sample as f32 / i16::MAX as f32).sum::<f32>() / channels as f32)
+            .collect();
+        DecodedTrack {
+            samples,
+            sample_rate,
+            loop_start: track.loop_start,
+            loop_end: track.loop_end,
+        }
+    }
+
+    /// frame heard after `elapsed` seconds, following the loop like `LoopDecoder`
+    pub fn frame_at(&self, elapsed: f32) -> usize {
+        let frame = (elapsed.max(0.0) * self.sample_rate as f32) as u64;
+        let end = self.loop_end.unwrap_or(self.samples.len() as u64).min(self.samples.len() as u64);
+        if frame < end || end <= self.loop_start {
+            return frame.min(end) as usize;
+        }
+        (self.loop_start + (frame - self.loop_start) % (end - self.loop_start)) as usize
+    }
+
+    /// loudness of `bands` log spaced frequency bands around `elapsed`, each in 0..=1
+    pub fn spectrum(&self, elapsed: f32, bands: usize) -> Vec<f32> {
+        const WINDOW: usize = 1024;
+        const MIN_FREQ: f32 = 50.0;
+        const MAX_FREQ: f32 = 12000.0;
+        const FLOOR_DB: f32 = -60.0;
+
+        let start = self.frame_at(elapsed).min(self.samples.len().saturating_sub(WINDOW));
+        let window = &self.samples[start..(start + WINDOW).min(self.samples.len())];
+        if window.is_empty() {
+            return vec![0.0; bands];
+        }
+
+        (0..bands).map(|band| {
+            let freq = MIN_FREQ * (MAX_FREQ / MIN_FREQ).powf((band as f32 + 0.5) / bands as f32);
+            // goertzel filter on a hann windowed slice
+            let coefficient = 2.0 * (std::f32::consts::TAU * freq / self.sample_rate as f32).cos();
+            let (mut previous, mut before_previous) = (0.0, 0.0);
+            for (i, sample) in window.iter().enumerate() {
+                let hann = 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / window.len() as f32).cos();
+                let current = sample * hann + coefficient * previous - before_previous;
+                before_previous = previous;
+                previous = current;
+            }
+            let power = previous * previous + before_previous * before_previous - coefficient * previous * before_previous;
+            let magnitude = power.max(0.0).sqrt() / (window.len() as f32 / 4.0);
+            let db = 20.0 * magnitude.max(1e-6).log10();
+            ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+        }).collect()
+    }
+}
+
+#[derive(Component)]
+struct MusicPlayer;
+#[derive(Component)]
+struct FadingOut(Timer);
+/// a new track ramps up to the bgm volume while the old one fades out
+#[derive(Component)]
+struct FadingIn(Timer);
+/// players left alone by the fades, they follow the bgm volume setting directly
+type SteadyPlayer = (With<MusicPlayer>, Without<FadingOut>, Without<FadingIn>);
+
+fn setup_music_library(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let library: Handle<MusicLibraryAsset> = asset_server.load("audio/music.bgm.ron");
+    commands.insert_resource(MusicLibrary(library));
+}
+
+fn load_heard_tracks(
+    mut commands: Commands,
+    save_dir: Res<SaveDir>,
+) {
+    commands.insert_resource(HeardTracks::load(&save_dir));
+}
+
+/// everything that decides which track should be playing
+#[derive(SystemParam)]
+struct MusicCue<'w> {
+    app_state: Res<'w, State<AppState>>,
+    library: Res<'w, MusicLibrary>,
+    libraries: Res<'w, Assets<MusicLibraryAsset>>,
+    runner: Option<Res<'w, StageRunner>>,
+    stage_list: Option<Res<'w, StageList>>,
+    stages: Res<'w, Assets<StageAsset>>,
+    dialogue_music: Option<Res<'w, DialogueMusic>>,
+}
+impl MusicCue<'_> {
+    fn library(&self) -> Option<&MusicLibraryAsset> {
+        self.libraries.get(&self.library.0)
+    }
+
+    fn wanted_track(&self, manager: &MusicManager) -> Option<String> {
+        let library = self.library()?;
+        match self.app_state.get() {
+            AppState::Loading => None,
+            // a retried practice run keeps its music going
+            AppState::Restarting => manager.current.clone(),
+            AppState::MainMenu => Some(manager.requested.clone().unwrap_or_else(|| library.menu.clone())),
+            AppState::InGame => {
+                let runner = self.runner.as_deref()?;
+                let stage = self.stage_list.as_ref()
+                    .and_then(|stage_list| stage_list.get(runner.extra, runner.stage))
+                    .and_then(|handle| self.stages.get(handle))?;
+                if let Some(music) = &self.dialogue_music
+                    && runner.current_boss() == music.boss
+                {
+                    return Some(music.track.clone());
+                }
+                let boss_track = runner.current_boss()
+                    .and_then(|boss| stage.bosses.get(boss))
+                    .and_then(|boss| boss.bgm.clone());
+                boss_track.or_else(|| stage.bgm.clone())
+            }
+        }
+    }
+}
+
+fn update_music(
+    mut commands: Commands,
+    mut manager: ResMut<MusicManager>,
+    cue: MusicCue,
+    mut heard: ResMut<HeardTracks>,
+    save_dir: Res<SaveDir>,
+) {
+    const CROSSFADE_SECS: f32 = 1.5;
+
+    let Some(library) = cue.library() else {
+        return;
+    };
+    let wanted = cue.wanted_track(&manager);
+    if wanted == manager.current {
+        return;
+    }
+
+    if let Some(player) = manager.player.take() {
+        commands.entity(player)
+            .remove::<FadingIn>()
+            .insert(FadingOut(Timer::from_seconds(CROSSFADE_SECS, TimerMode::Once)));
+    }
+    let track = wanted.as_deref()
+        .and_then(|id| library.track(id))
+        .and_then(|track| Some((track, track.source.clone()?)));
+    if let Some((track, source)) = track {
+        let player = commands.spawn((
+            MusicPlayer,
+            AudioPlayer(source),
+            PlaybackSettings::DESPAWN.with_volume(Volume::new(0.0)),
+            FadingIn(Timer::from_seconds(CROSSFADE_SECS, TimerMode::Once)),
+        )).id();
+        manager.player = Some(player);
+
+        if !heard.contains(&track.id) {
+            heard.0.push(track.id.clone());
+            heard.save(&save_dir);
+        }
+    }
+    manager.current = wanted;
+    manager.elapsed = 0.0;
+}
+
+fn advance_music_clock(
+    time: Res<Time<Real>>,
+    mut manager: ResMut<MusicManager>,
+) {
+    manager.elapsed += time.delta_secs();
+}
+
+fn fade_out_music(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut query: Query<(Entity, &mut FadingOut, Option<&AudioSink>)>,
+    volume: Res<BgmVolume>,
+) {
+    for (entity, mut fade, sink) in query.iter_mut() {
+        fade.0.tick(time.delta());
+        if fade.0.finished() {
+            commands.entity(entity).despawn();
+        } else if let Some(sink) = sink {
+            sink.set_volume(volume.0 * fade.0.fraction_remaining());
+        }
+    }
+}
+
+fn fade_in_music(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut query: Query<(Entity, &mut FadingIn, Option<&AudioSink>)>,
+    volume: Res<BgmVolume>,
+) {
+    for (entity, mut fade, sink) in query.iter_mut() {
+        // the ramp starts once the track is actually playing
+        let Some(sink) = sink else {
+            continue;
+        };
+        fade.0.tick(time.delta());
+        sink.set_volume(volume.0 * fade.0.fraction());
+        if fade.0.finished() {
+            commands.entity(entity).remove::<FadingIn>();
+        }
+    }
+}
+
+fn pause_music(
+    query: Query<&AudioSink, With<MusicPlayer>>,
+) {
+    for sink in query.iter() {
+        sink.pause();
+    }
+}
+
+fn resume_music(
+    query: Query<&AudioSink, With<MusicPlayer>>,
+) {
+    for sink in query.iter() {
+        sink.play();
+    }
+}
+
+fn apply_bgm_volume(
+    query: Query<&AudioSink, SteadyPlayer>,
+    volume: Res<BgmVolume>,
+) {
+    for sink in query.iter() {
+        sink.set_volume(volume.0);
+    }
+}
+
+pub struct MusicPlugin;
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_audio_source::<MusicTrack>()
+            .init_asset::<MusicLibraryAsset>()
+            .init_asset_loader::<MusicLibraryLoader>()
+            .init_resource::<BgmVolume>()
+            .init_resource::<MusicManager>()
+            .init_resource::<HeardTracks>()
+            .add_systems(Startup, (setup_music_library, load_heard_tracks))
+            .add_systems(OnEnter(GameState::Paused), pause_music)
+            .add_systems(OnExit(GameState::Paused), resume_music)
+            .add_systems(Update, (
+                update_music,
+                advance_music_clock.run_if(not(in_state(GameState::Paused))),
+                fade_out_music,
+                fade_in_music,
+                apply_bgm_volume.run_if(resource_changed::<BgmVolume>),
+            ));
+    }
+}
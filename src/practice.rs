@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::character::{Character, PlayerCharacter};
+use crate::difficulty::{Difficulty, PlayerDifficulty};
+use crate::event::{SpellCardCaptured, SpellCardDeclared};
+use crate::replay::ReplayPlayback;
+use crate::save::SaveDir;
+use crate::stage::{StageAsset, StageFinished};
+use crate::state::AppState;
+
+const PRACTICE_FILE: &str = "practice.ron";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PracticeTarget {
+    Stage(usize),
+    /// a single boss phase, fought without the waves leading up to it
+    SpellCard {
+        stage: usize,
+        boss: usize,
+        phase: usize,
+    },
+}
+/// present while a practice run is played, its lives, bombs and power replace the difficulty's
+#[derive(Resource, Clone)]
+pub struct PracticeRun {
+    pub target: PracticeTarget,
+    pub lives: i32,
+    pub bombs: i32,
+    pub power: i32,
+}
+impl PracticeRun {
+    pub fn stage(&self) -> usize {
+        match self.target {
+            PracticeTarget::Stage(stage) => stage,
+            PracticeTarget::SpellCard { stage, .. } => stage,
+        }
+    }
+}
+
+/// restart the current practice run from its beginning without leaving the game
+#[derive(Event)]
+pub struct RetryPractice;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SpellCardRecord {
+    pub name: String,
+    pub difficulty: Difficulty,
+    pub character: Character,
+    pub attempts: u32,
+    pub captures: u32,
+}
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct PracticeRecords {
+    /// highest stage index reached in a regular run
+    pub reached_stage: usize,
+    pub spell_cards: Vec<SpellCardRecord>,
+}
+impl PracticeRecords {
+    pub fn spell_card(&self, name: &str, difficulty: Difficulty, character: Character) -> Option<&SpellCardRecord> {
+        self.spell_cards
+            .iter()
+            .find(|record| record.name == name && record.difficulty == difficulty && record.character == character)
+    }
+    fn spell_card_mut(&mut self, name: &str, difficulty: Difficulty, character: Character) -> &mut SpellCardRecord {
+        let index = match self.spell_cards
+            .iter()
+            .position(|record| record.name == name && record.difficulty == difficulty && record.character == character)
+        {
+            Some(index) => index,
+            None => {
+                self.spell_cards.push(SpellCardRecord {
+                    name: name.to_string(),
+                    difficulty,
+                    character,
+                    attempts: 0,
+                    captures: 0,
+                });
+                self.spell_cards.len() - 1
+            }
+        };
+        &mut self.spell_cards[index]
+    }
+    pub fn load(dir: &SaveDir) -> Self {
+        dir.load(PRACTICE_FILE).unwrap_or_default()
+    }
+    pub fn save(&self, dir: &SaveDir) {
+        dir.save(PRACTICE_FILE, self);
+    }
+}
+
+/// a boss phase with a spell card, as listed by Spell Practice
+pub struct PracticeSpellCard {
+    pub stage: usize,
+    pub boss: usize,
+    pub phase: usize,
+    pub boss_name: String,
+    pub name: String,
+}
+/// every spell card of the given stages, in the order they are fought
+pub fn practice_spell_cards<'a>(stages: impl IntoIterator<Item = &'a StageAsset>) -> Vec<PracticeSpellCard> {
+    let mut spell_cards = Vec::new();
+    for (stage_index, stage) in stages.into_iter().enumerate() {
+        for (boss_index, boss) in stage.bosses.iter().enumerate() {
+            for (phase_index, phase) in boss.phases.iter().enumerate() {
+                if let Some(name) = &phase.spell_card {
+                    spell_cards.push(PracticeSpellCard {
+                        stage: stage_index,
+                        boss: boss_index,
+                        phase: phase_index,
+                        boss_name: boss.name.clone(),
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+    }
+    spell_cards
+}
+
+fn load_practice_records(
+    mut commands: Commands,
+    save_dir: Res<SaveDir>,
+) {
+    commands.insert_resource(PracticeRecords::load(&save_dir));
+}
+
+fn record_spell_cards(
+    mut declared: EventReader<SpellCardDeclared>,
+    mut captured: EventReader<SpellCardCaptured>,
+    difficulty: Res<PlayerDifficulty>,
+    character: Res<PlayerCharacter>,
+    mut records: ResMut<PracticeRecords>,
+    save_dir: Res<SaveDir>,
+) {
+    if declared.is_empty() && captured.is_empty() {
+        return;
+    }
+    for event in declared.read() {
+        records.spell_card_mut(&event.name, difficulty.0, character.0).attempts += 1;
+    }
+    for event in captured.read() {
+        records.spell_card_mut(&event.name, difficulty.0, character.0).captures += 1;
+    }
+    records.save(&save_dir);
+}
+
+fn record_stage_reached(
+    mut stage_finished: EventReader<StageFinished>,
+    difficulty: Res<PlayerDifficulty>,
+    mut records: ResMut<PracticeRecords>,
+    save_dir: Res<SaveDir>,
+) {
+    // the extra stage is not part of the stage order
+    if difficulty.0 == Difficulty::Extra {
+        stage_finished.clear();
+        return;
+    }
+    for event in stage_finished.read() {
+        records.reached_stage = records.reached_stage.max(event.stage + 1);
+    }
+    records.save(&save_dir);
+}
+
+/// leave the run for one update, so the retry goes through the same exit and enter as a fresh start
+fn retry_practice(
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    next_state.set(AppState::Restarting);
+}
+
+fn finish_restart(
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    next_state.set(AppState::InGame);
+}
+
+fn end_practice_run(
+    mut commands: Commands,
+) {
+    commands.remove_resource::<PracticeRun>();
+}
+
+pub struct PracticePlugin;
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<RetryPractice>()
+            .add_systems(Startup, load_practice_records)
+            .add_systems(OnEnter(AppState::MainMenu), end_practice_run)
+            .add_systems(OnEnter(AppState::Restarting), finish_restart)
+            .add_systems(PreUpdate, retry_practice
+                .run_if(in_state(AppState::InGame).and(resource_exists::<PracticeRun>).and(on_event::<RetryPractice>)))
+            // replays would count every attempt a second time
+            .add_systems(FixedUpdate, (
+                record_spell_cards,
+                record_stage_reached.run_if(on_event::<StageFinished>.and(not(resource_exists::<PracticeRun>))),
+            ).run_if(not(resource_exists::<ReplayPlayback>)));
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::character::{Character, PlayerCharacter};
+use crate::difficulty::{Difficulty, PlayerDifficulty};
+use crate::input::{Action, InputFrame};
+use crate::practice::PracticeRun;
+use crate::resource::{PlayerSpellCard, SpellCard};
+use crate::rng::GameRng;
+use crate::save::{today, SaveDir};
+use crate::state::{AppState, GameState};
+use crate::tick::TickRate;
+
+/// bumped whenever the file layout or the meaning of recorded inputs changes
+pub const REPLAY_VERSION: u32 = 2;
+const REPLAY_DIR: &str = "replays";
+const MAX_REPLAYS: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReplayFile {
+    pub version: u32,
+    pub date: String,
+    pub seed: u64,
+    pub tick_rate: f64,
+    pub character: Character,
+    pub difficulty: Difficulty,
+    pub spell_card: SpellCard,
+    /// run-length encoded (buttons, ticks) pairs
+    pub inputs: Vec<(u8, u32)>,
+}
+impl ReplayFile {
+    pub fn ticks(&self) -> u32 {
+        self.inputs.iter().map(|(_, ticks)| ticks).sum()
+    }
+    pub fn duration_secs(&self) -> u32 {
+        (self.ticks() as f64 / self.tick_rate) as u32
+    }
+    pub fn frames(&self) -> Vec<InputFrame> {
+        self.inputs
+            .iter()
+            .flat_map(|&(buttons, ticks)| std::iter::repeat_n(InputFrame(buttons), ticks as usize))
+            .collect()
+    }
+}
+fn encode_frames(frames: &[InputFrame]) -> Vec<(u8, u32)> {
+    let mut inputs: Vec<(u8, u32)> = Vec::new();
+    for frame in frames {
+        match inputs.last_mut() {
+            Some((buttons, ticks)) if *buttons == frame.0 => *ticks += 1,
+            _ => inputs.push((frame.0, 1)),
+        }
+    }
+    inputs
+}
+
+/// saved replays ordered by file name, files from another version are skipped
+pub fn list_replays(save_dir: &SaveDir) -> Vec<(String, ReplayFile)> {
+    let Ok(entries) = std::fs::read_dir(save_dir.0.join(REPLAY_DIR)) else {
+        return Vec::new();
+    };
+    let mut file_names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".ron"))
+        .collect();
+    file_names.sort();
+
+    file_names
+        .into_iter()
+        .filter_map(|name| {
+            let replay: ReplayFile = save_dir.load(&format!("{}/{}", REPLAY_DIR, name))?;
+            if replay.version != REPLAY_VERSION {
+                warn!("Skipping replay {} recorded with version {}", name, replay.version);
+                return None;
+            }
+            Some((name, replay))
+        })
+        .collect()
+}
+
+/// write the replay into the first free slot, returning its file name
+pub fn save_replay(save_dir: &SaveDir, replay: &ReplayFile) -> Option<String> {
+    let replay_dir = save_dir.0.join(REPLAY_DIR);
+    let name = (1..=MAX_REPLAYS)
+        .map(|slot| format!("replay{:03}.ron", slot))
+        .find(|name| !replay_dir.join(name).exists())?;
+    save_dir.save(&format!("{}/{}", REPLAY_DIR, name), replay);
+    Some(name)
+}
+
+/// inputs of the current run, recording stops at the first game over or the stage clear
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    frames: Vec<InputFrame>,
+    stopped: bool,
+    /// frames the last saved file holds, a save from the pause menu does not block a longer one later
+    saved: Option<usize>,
+}
+impl ReplayRecorder {
+    pub fn record(&mut self, frame: InputFrame) {
+        if !self.stopped {
+            self.frames.push(frame);
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub seed: u64,
+    pub tick_rate: f64,
+    frames: Vec<InputFrame>,
+    cursor: usize,
+}
+impl ReplayPlayback {
+    pub fn new(replay: &ReplayFile) -> Self {
+        ReplayPlayback {
+            seed: replay.seed,
+            tick_rate: replay.tick_rate,
+            frames: replay.frames(),
+            cursor: 0,
+        }
+    }
+    pub fn next_frame(&mut self) -> Option<InputFrame> {
+        let frame = self.frames.get(self.cursor).copied();
+        self.cursor += 1;
+        frame
+    }
+    pub fn finished(&self) -> bool {
+        self.cursor > self.frames.len()
+    }
+}
+
+#[derive(Event)]
+pub struct SaveReplayRequested;
+
+fn start_recording(
+    mut commands: Commands,
+    playback: Option<Res<ReplayPlayback>>,
+    practice: Option<Res<PracticeRun>>,
+) {
+    commands.remove_resource::<ReplayRecorder>();
+    if playback.is_none() && practice.is_none() {
+        commands.insert_resource(ReplayRecorder::default());
+    }
+}
+
+fn stop_recording(
+    recorder: Option<ResMut<ReplayRecorder>>,
+) {
+    if let Some(mut recorder) = recorder {
+        recorder.stopped = true;
+    }
+}
+
+/// what a replay needs besides the inputs to play the run again
+#[derive(SystemParam)]
+struct ReplaySettings<'w> {
+    rng: Res<'w, GameRng>,
+    tick_rate: Res<'w, TickRate>,
+    character: Res<'w, PlayerCharacter>,
+    difficulty: Res<'w, PlayerDifficulty>,
+    spell_card: Res<'w, PlayerSpellCard>,
+}
+impl ReplaySettings<'_> {
+    fn replay(&self, frames: &[InputFrame]) -> ReplayFile {
+        ReplayFile {
+            version: REPLAY_VERSION,
+            date: today(),
+            seed: self.rng.seed(),
+            tick_rate: self.tick_rate.0,
+            character: self.character.0,
+            difficulty: self.difficulty.0,
+            spell_card: self.spell_card.0,
+            inputs: encode_frames(frames),
+        }
+    }
+}
+
+fn save_requested_replay(
+    mut events: EventReader<SaveReplayRequested>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    settings: ReplaySettings,
+    save_dir: Res<SaveDir>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+    if recorder.saved == Some(recorder.frames.len()) {
+        info!("Replay already saved");
+        return;
+    }
+
+    let replay = settings.replay(&recorder.frames);
+    match save_replay(&save_dir, &replay) {
+        Some(name) => {
+            info!("Replay saved as {}", name);
+            recorder.saved = Some(recorder.frames.len());
+        }
+        None => warn!("No free replay slot left"),
+    }
+}
+
+fn replay_fast_forward(
+    actions: Res<ButtonInput<Action>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    const FAST_FORWARD_SPEED: f32 = 4.0;
+    let speed = if actions.pressed(Action::Skip) {
+        FAST_FORWARD_SPEED
+    } else {
+        1.0
+    };
+    if time.relative_speed() != speed {
+        time.set_relative_speed(speed);
+    }
+}
+
+fn stop_playback(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    commands.remove_resource::<ReplayPlayback>();
+    time.set_relative_speed(1.0);
+}
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SaveReplayRequested>()
+            // a replay file has no room for practice settings, so practice runs are not recorded
+            .add_systems(OnEnter(AppState::InGame), start_recording)
+            .add_systems(OnEnter(GameState::GameOver), stop_recording)
+            .add_systems(OnEnter(GameState::StageClear), stop_recording)
+            .add_systems(OnEnter(AppState::MainMenu), stop_playback)
+            .add_systems(Update, (
+                save_requested_replay.run_if(on_event::<SaveReplayRequested>),
+                replay_fast_forward.run_if(resource_exists::<ReplayPlayback>),
+            ));
+    }
+}
@@ -1,5 +1,6 @@
 use bevy::asset::Handle;
 use bevy::prelude::{Font, Resource};
+use serde::{Deserialize, Serialize};
 
 #[derive(Resource)]
 pub struct AsciiFont(pub Handle<Font>);
@@ -9,4 +10,14 @@ pub struct AsciiBoldFont(pub Handle<Font>);
 pub struct WindowSize {
     pub width: f32,
     pub height: f32,
-}
\ No newline at end of file
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum SpellCard {
+    #[default]
+    FantasyOrb,
+    HomingAmulet,
+    MasterSpark,
+    StardustReverie,
+}
+#[derive(Resource, Clone, Copy)]
+pub struct PlayerSpellCard(pub SpellCard);
\ No newline at end of file
@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::replay::ReplayPlayback;
+use crate::state::AppState;
+
+/// seed requested with `--seed <n>` on the command line, a fresh one is picked per run otherwise
+#[derive(Resource, Default, Clone, Copy)]
+pub struct SeedOverride(pub Option<u64>);
+impl SeedOverride {
+    pub fn from_args() -> Self {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let value = match arg.strip_prefix("--seed=") {
+                Some(value) => Some(value.to_string()),
+                None if arg == "--seed" => args.next(),
+                None => continue,
+            };
+            match value.as_deref().map(str::parse::<u64>) {
+                Some(Ok(seed)) => return SeedOverride(Some(seed)),
+                _ => warn!("Ignoring invalid --seed argument"),
+            }
+        }
+        SeedOverride(None)
+    }
+}
+
+/// every random decision that can change the outcome of a run draws from here
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+impl std::ops::Deref for GameRng {
+    type Target = StdRng;
+    fn deref(&self) -> &StdRng {
+        &self.rng
+    }
+}
+impl std::ops::DerefMut for GameRng {
+    fn deref_mut(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
+/// particles and other effects, kept apart so they never shift the gameplay stream
+#[derive(Resource)]
+pub struct CosmeticRng(pub StdRng);
+impl std::ops::Deref for CosmeticRng {
+    type Target = StdRng;
+    fn deref(&self) -> &StdRng {
+        &self.0
+    }
+}
+impl std::ops::DerefMut for CosmeticRng {
+    fn deref_mut(&mut self) -> &mut StdRng {
+        &mut self.0
+    }
+}
+
+fn seed_rng(
+    mut commands: Commands,
+    seed_override: Res<SeedOverride>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    let seed = match playback {
+        Some(playback) => playback.seed,
+        None => seed_override.0.unwrap_or_else(rand::random),
+    };
+    info!("Game seed: {}", seed);
+    commands.insert_resource(GameRng::new(seed));
+    commands.insert_resource(CosmeticRng(StdRng::seed_from_u64(seed.rotate_left(32) ^ 0x9E37_79B9_7F4A_7C15)));
+}
+
+pub struct RngPlugin;
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<SeedOverride>() {
+            app.insert_resource(SeedOverride::from_args());
+        }
+        app.add_systems(OnEnter(AppState::InGame), seed_rng);
+    }
+}
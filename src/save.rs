@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use bevy::utils::SystemTime;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// directory holding every persistent file, `ASCIIHOU_DATA_DIR` overrides the platform default
+#[derive(Resource, Clone)]
+pub struct SaveDir(pub PathBuf);
+impl Default for SaveDir {
+    fn default() -> Self {
+        if let Some(dir) = std::env::var_os("ASCIIHOU_DATA_DIR") {
+            return SaveDir(PathBuf::from(dir));
+        }
+        SaveDir(platform_data_dir().join("asciihou"))
+    }
+}
+impl SaveDir {
+    pub fn load<T: DeserializeOwned>(&self, file_name: &str) -> Option<T> {
+        let path = self.0.join(file_name);
+        let bytes = std::fs::read(&path).ok()?;
+        match ron::de::from_bytes::<T>(&bytes) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!("Failed to parse {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+    pub fn save<T: Serialize>(&self, file_name: &str, value: &T) {
+        let path = self.0.join(file_name);
+        let dir = path.parent().unwrap_or(&self.0);
+        let result = std::fs::create_dir_all(dir)
+            .map_err(|err| err.to_string())
+            .and_then(|_| ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()).map_err(|err| err.to_string()))
+            .and_then(|text| std::fs::write(&path, text).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            warn!("Failed to write {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn platform_data_dir() -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from).unwrap_or(home)
+    } else if cfg!(target_os = "macos") {
+        home.join("Library/Application Support")
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".local/share"))
+    }
+}
+
+/// today's date as YYYY-MM-DD (UTC)
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / 86400)
+        .unwrap_or(0) as i64;
+
+    // days since 1970-01-01 to civil date
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveDir>();
+    }
+}
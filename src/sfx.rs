@@ -0,0 +1,188 @@
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::asset::io::Reader;
+use bevy::audio::Volume;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::ascii_animation::CustomAssetLoaderError;
+use crate::event::{BombUsed, EnemyHit, EnemyKilled, Grazed, ItemCollected, LaserFired, LaserTelegraphStarted, PlayerHit, PlayerShot};
+use crate::stage::BossDefeated;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum SoundEffect {
+    PlayerShot,
+    EnemyHit,
+    EnemyDeath,
+    Graze,
+    ItemPickup,
+    PlayerDeath,
+    Bomb,
+    LaserTelegraph,
+    LaserFire,
+    MenuMove,
+    MenuConfirm,
+    MenuCancel,
+}
+
+#[derive(Default)]
+pub struct SoundBankLoader;
+impl AssetLoader for SoundBankLoader {
+    type Asset = SoundBankAsset;
+    type Settings = ();
+    type Error = CustomAssetLoaderError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut bank = ron::de::from_bytes::<SoundBankAsset>(&bytes)?;
+        for sound in bank.sounds.values_mut() {
+            sound.source = load_context.load(&sound.path);
+        }
+        Ok(bank)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sfx.ron"]
+    }
+}
+
+#[derive(Asset, Deserialize, Serialize, TypePath)]
+pub struct SoundBankAsset {
+    pub sounds: HashMap<SoundEffect, SoundDef>,
+}
+#[derive(Deserialize, Serialize)]
+pub struct SoundDef {
+    pub path: String,
+    #[serde(default = "default_sound_volume")]
+    pub volume: f32,
+    /// minimum seconds between two plays, requests in between are dropped
+    #[serde(default)]
+    pub cooldown: f32,
+    #[serde(skip)]
+    pub source: Handle<AudioSource>,
+}
+fn default_sound_volume() -> f32 {
+    1.0
+}
+
+#[derive(Resource)]
+pub struct SoundBank(pub Handle<SoundBankAsset>);
+
+/// sfx channel volume, multiplied with the per-sound volume of the bank
+#[derive(Resource)]
+pub struct SfxVolume(pub f32);
+impl Default for SfxVolume {
+    fn default() -> Self {
+        SfxVolume(1.0)
+    }
+}
+
+#[derive(Event)]
+pub struct PlaySfx(pub SoundEffect);
+
+/// real time each sound was last played at
+#[derive(Resource, Default)]
+struct SfxThrottle(HashMap<SoundEffect, Duration>);
+
+fn setup_sound_bank(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let bank: Handle<SoundBankAsset> = asset_server.load("audio/sound_bank.sfx.ron");
+    commands.insert_resource(SoundBank(bank));
+}
+
+fn forward_events<E: Event>(events: &mut EventReader<E>, sfx: &mut EventWriter<PlaySfx>, effect: SoundEffect) {
+    // one request per update is enough, the rest would be throttled anyway
+    if !events.is_empty() {
+        events.clear();
+        sfx.send(PlaySfx(effect));
+    }
+}
+
+/// gameplay events that come with a sound
+#[derive(SystemParam)]
+struct GameplayEvents<'w, 's> {
+    player_shot: EventReader<'w, 's, PlayerShot>,
+    enemy_hit: EventReader<'w, 's, EnemyHit>,
+    enemy_killed: EventReader<'w, 's, EnemyKilled>,
+    boss_defeated: EventReader<'w, 's, BossDefeated>,
+    grazed: EventReader<'w, 's, Grazed>,
+    item_collected: EventReader<'w, 's, ItemCollected>,
+    player_hit: EventReader<'w, 's, PlayerHit>,
+    bomb_used: EventReader<'w, 's, BombUsed>,
+    laser_telegraph: EventReader<'w, 's, LaserTelegraphStarted>,
+    laser_fired: EventReader<'w, 's, LaserFired>,
+}
+
+fn gameplay_sfx(
+    mut events: GameplayEvents,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    use SoundEffect::*;
+    forward_events(&mut events.player_shot, &mut sfx, PlayerShot);
+    forward_events(&mut events.enemy_hit, &mut sfx, EnemyHit);
+    forward_events(&mut events.enemy_killed, &mut sfx, EnemyDeath);
+    forward_events(&mut events.boss_defeated, &mut sfx, EnemyDeath);
+    forward_events(&mut events.grazed, &mut sfx, Graze);
+    forward_events(&mut events.item_collected, &mut sfx, ItemPickup);
+    forward_events(&mut events.player_hit, &mut sfx, PlayerDeath);
+    forward_events(&mut events.bomb_used, &mut sfx, Bomb);
+    forward_events(&mut events.laser_telegraph, &mut sfx, LaserTelegraph);
+    forward_events(&mut events.laser_fired, &mut sfx, LaserFire);
+}
+
+fn play_sfx(
+    mut commands: Commands,
+    mut requests: EventReader<PlaySfx>,
+    mut throttle: ResMut<SfxThrottle>,
+    time: Res<Time<Real>>,
+    volume: Res<SfxVolume>,
+    bank: Res<SoundBank>,
+    banks: Res<Assets<SoundBankAsset>>,
+) {
+    let Some(bank) = banks.get(&bank.0) else {
+        requests.clear();
+        return;
+    };
+    let now = time.elapsed();
+
+    for PlaySfx(effect) in requests.read() {
+        let Some(sound) = bank.sounds.get(effect) else {
+            continue
+        };
+        let throttled = throttle.0.get(effect)
+            .is_some_and(|last_played| now.saturating_sub(*last_played).as_secs_f32() < sound.cooldown);
+        if throttled {
+            continue
+        }
+        throttle.0.insert(*effect, now);
+
+        commands.spawn((
+            AudioPlayer(sound.source.clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::new(sound.volume * volume.0)),
+        ));
+    }
+}
+
+pub struct SfxPlugin;
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<SoundBankAsset>()
+            .init_asset_loader::<SoundBankLoader>()
+            .init_resource::<SfxVolume>()
+            .init_resource::<SfxThrottle>()
+            .add_event::<PlaySfx>()
+            .add_systems(Startup, setup_sound_bank)
+            .add_systems(Update, (
+                gameplay_sfx,
+                play_sfx,
+            ).chain());
+    }
+}
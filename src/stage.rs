@@ -0,0 +1,239 @@
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::asset::io::Reader;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ascii_animation::CustomAssetLoaderError;
+use crate::dialogue::DialogueAsset;
+
+#[derive(Default)]
+pub struct StageLoader;
+impl AssetLoader for StageLoader {
+    type Asset = StageAsset;
+    type Settings = ();
+    type Error = CustomAssetLoaderError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut stage = ron::de::from_bytes::<StageAsset>(&bytes)?;
+        let paths: Vec<String> = stage.dialogue
+            .iter()
+            .chain(stage.bosses.iter().flat_map(|boss| boss.dialogue_before.iter().chain(boss.dialogue_after.iter())))
+            .cloned()
+            .collect();
+        for path in paths {
+            let handle = load_context.load(path.clone());
+            stage.dialogues.push((path, handle));
+        }
+        Ok(stage)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["stage.ron"]
+    }
+}
+
+#[derive(Asset, Deserialize, Serialize, TypePath)]
+pub struct StageAsset {
+    pub name: String,
+    pub waves: Vec<WaveDef>,
+    #[serde(default)]
+    pub bosses: Vec<BossDef>,
+    /// music track id, see `MusicLibraryAsset`
+    #[serde(default)]
+    pub bgm: Option<String>,
+    /// dialogue path, shown before the first wave
+    #[serde(default)]
+    pub dialogue: Option<String>,
+    /// every dialogue of the script, loaded along with it
+    #[serde(skip)]
+    pub dialogues: Vec<(String, Handle<DialogueAsset>)>,
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub struct WaveDef {
+    /// seconds since the stage started
+    pub time: f32,
+    /// one enemy is spawned per position
+    pub spawns: Vec<Vec2>,
+    /// seconds between two consecutive spawns of this wave
+    #[serde(default)]
+    pub interval: f32,
+    pub glyph: String,
+    pub color: String,
+    pub movement: MovementDef,
+    pub health: i32,
+    /// points for killing one enemy of this wave
+    pub score: u64,
+    pub emitter: EmitterDef,
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub struct BossDef {
+    /// seconds since the stage started, the stage clock stops while a boss is alive
+    pub time: f32,
+    pub name: String,
+    pub glyph: String,
+    pub color: String,
+    /// where the boss settles after entering from the top
+    pub position: Vec2,
+    /// bonus for defeating the boss
+    pub score: u64,
+    pub phases: Vec<BossPhaseDef>,
+    /// music track id played from the boss' entrance until the next boss or the end of the stage
+    #[serde(default)]
+    pub bgm: Option<String>,
+    /// dialogue path, shown as the boss enters
+    #[serde(default)]
+    pub dialogue_before: Option<String>,
+    /// dialogue path, shown once the boss is defeated
+    #[serde(default)]
+    pub dialogue_after: Option<String>,
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub struct BossPhaseDef {
+    pub health: i32,
+    /// seconds before the phase ends on its own
+    pub timeout: f32,
+    #[serde(default)]
+    pub spell_card: Option<String>,
+    pub emitters: Vec<EmitterDef>,
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub enum MovementDef {
+    Linear(Vec2),
+    TowardPlayer(f32),
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub enum AimDef {
+    Player,
+    Fixed(Vec2),
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub enum EmitterDef {
+    None,
+    Single {
+        bullet: BulletDef,
+        aim: AimDef,
+        speed: f32,
+        cooldown: f32,
+        times: i32,
+    },
+    Fan {
+        bullet: BulletDef,
+        aim: AimDef,
+        speed: f32,
+        num_bullets: i32,
+        angle_deg: f32,
+        cooldown: f32,
+        times: i32,
+    },
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub struct BulletDef {
+    pub kind: BulletKindDef,
+    pub glyph: String,
+    pub color: String,
+}
+#[derive(Deserialize, Serialize, Clone)]
+pub enum BulletKindDef {
+    Normal,
+    Homing {
+        speed: f32,
+        rotate_speed: f32, // rad/s
+    },
+    Spiral {
+        radius: f32,
+        radius_growth: f32,
+        angular_speed: f32, // rad/s
+        angle: f32, // rad
+        forward_speed: f32,
+    },
+    Laser {
+        telegraph: f32,
+        duration: f32,
+        length: f32,
+    },
+}
+pub struct ScheduledSpawn {
+    pub time: f32,
+    pub wave: usize,
+    pub spawn: usize,
+}
+impl StageAsset {
+    pub fn dialogue(&self, path: &str) -> Option<&Handle<DialogueAsset>> {
+        self.dialogues
+            .iter()
+            .find(|(dialogue, _)| dialogue == path)
+            .map(|(_, handle)| handle)
+    }
+    /// flatten every wave into single enemy spawns ordered by time
+    pub fn spawn_schedule(&self) -> Vec<ScheduledSpawn> {
+        let mut schedule: Vec<ScheduledSpawn> = self.waves
+            .iter()
+            .enumerate()
+            .flat_map(|(wave_index, wave)| {
+                (0..wave.spawns.len()).map(move |spawn_index| ScheduledSpawn {
+                    time: wave.time + wave.interval * spawn_index as f32,
+                    wave: wave_index,
+                    spawn: spawn_index,
+                })
+            })
+            .collect();
+        schedule.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+        schedule
+    }
+}
+
+#[derive(Event)]
+pub struct StageFinished {
+    pub stage: usize,
+}
+#[derive(Event)]
+pub struct BossDefeated {
+    pub name: String,
+    pub position: Vec2,
+}
+#[derive(Resource)]
+pub struct StageList {
+    pub stages: Vec<Handle<StageAsset>>,
+    /// played on its own at Extra difficulty
+    pub extra: Handle<StageAsset>,
+}
+impl StageList {
+    /// script of a run's stage, the extra stage is the only stage of an extra run
+    pub fn get(&self, extra: bool, stage: usize) -> Option<&Handle<StageAsset>> {
+        if extra {
+            (stage == 0).then_some(&self.extra)
+        } else {
+            self.stages.get(stage)
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Handle<StageAsset>> {
+        self.stages.iter().chain(std::iter::once(&self.extra))
+    }
+}
+fn setup_stages(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let stage_1: Handle<StageAsset> = asset_server.load("stage/stage1.stage.ron");
+    let extra: Handle<StageAsset> = asset_server.load("stage/extra.stage.ron");
+    commands.insert_resource(StageList {
+        stages: vec![stage_1],
+        extra,
+    });
+}
+pub struct StagePlugin;
+impl Plugin for StagePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<StageAsset>()
+            .init_asset_loader::<StageLoader>()
+            .add_event::<StageFinished>()
+            .add_event::<BossDefeated>()
+            .add_systems(Startup, setup_stages);
+    }
+}
@@ -8,6 +8,8 @@ pub enum AppState {
     MainMenu,
     // #[default]
     InGame,
+    /// passed through for a single update when a practice run is retried
+    Restarting,
 }
 #[derive(SubStates, Debug, Clone, PartialEq, Eq, Hash, Default)]
 #[source(AppState = AppState::InGame)]
@@ -15,4 +17,9 @@ pub enum GameState {
     #[default]
     Running,
     Paused,
+    GameOver,
+    /// a dialogue of the stage script is shown over the frozen field
+    Dialogue,
+    /// the stage of a regular run is over, only the way back to the title is left
+    StageClear,
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::TimestepMode;
+use crate::replay::ReplayPlayback;
+use crate::state::AppState;
+
+/// simulation ticks per second, insert before `TickRatePlugin` to change it
+#[derive(Resource, Clone, Copy)]
+pub struct TickRate(pub f64);
+impl Default for TickRate {
+    fn default() -> Self {
+        TickRate(60.0)
+    }
+}
+impl TickRate {
+    pub fn timestep(&self) -> f32 {
+        (1.0 / self.0) as f32
+    }
+}
+
+fn apply_tick_rate(
+    rate: f64,
+    fixed_time: &mut Time<Fixed>,
+    timestep_mode: &mut TimestepMode,
+) {
+    fixed_time.set_timestep_hz(rate);
+    *timestep_mode = TimestepMode::Fixed {
+        dt: TickRate(rate).timestep(),
+        substeps: 1,
+    };
+}
+
+/// replays run at the rate they were recorded with
+fn sync_tick_rate(
+    tick_rate: Res<TickRate>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut timestep_mode: ResMut<TimestepMode>,
+) {
+    let rate = playback.map(|playback| playback.tick_rate).unwrap_or(tick_rate.0);
+    apply_tick_rate(rate, &mut fixed_time, &mut timestep_mode);
+}
+
+/// add before `RapierPhysicsPlugin` so physics steps with the same fixed timestep
+pub struct TickRatePlugin;
+impl Plugin for TickRatePlugin {
+    fn build(&self, app: &mut App) {
+        let tick_rate = *app.world_mut().get_resource_or_insert_with(TickRate::default);
+        let mut fixed_time = Time::<Fixed>::default();
+        let mut timestep_mode = TimestepMode::default();
+        apply_tick_rate(tick_rate.0, &mut fixed_time, &mut timestep_mode);
+        app
+            .insert_resource(fixed_time)
+            .insert_resource(timestep_mode)
+            .add_systems(OnEnter(AppState::InGame), sync_tick_rate);
+    }
+}
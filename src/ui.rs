@@ -1,10 +1,26 @@
 use crate::resource::WindowSize;
-use crate::resource::{AsciiBoldFont, AsciiFont};
+use crate::resource::{AsciiBoldFont, AsciiFont, PlayerSpellCard, SpellCard};
+use crate::character::{Character, PlayerCharacter};
+use crate::difficulty::{Difficulty, PlayerDifficulty};
+use crate::input::{gamepad_buttons, key_name, Action, KeyBindings};
+use crate::high_score::{HighScores, PendingHighScore, HIGH_SCORE_ENTRIES};
+use crate::replay::{list_replays, ReplayFile, ReplayPlayback, ReplayRecorder, SaveReplayRequested};
+use crate::config::{Config, Language, RESOLUTIONS};
+use crate::music::{DecodedTrack, HeardTracks, MusicLibrary, MusicLibraryAsset, MusicManager, MusicTrack};
+use crate::extra::ClearRecords;
+use crate::dialogue::{ActiveDialogue, DialogueAsset, ScreenFade};
+use crate::practice::{practice_spell_cards, PracticeRecords, PracticeRun, PracticeTarget, RetryPractice};
+use crate::save::SaveDir;
+use crate::stage::{StageAsset, StageFinished, StageList};
+use crate::sfx::{PlaySfx, SoundEffect};
 use crate::state::AppState;
 use crate::state::GameState;
 use bevy::color::palettes::basic::*;
 use bevy::input::common_conditions::{input_just_pressed};
 use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::text::TextBounds;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
 
 #[derive(Component)]
 pub struct PlayerLivesText;
@@ -16,6 +32,16 @@ pub struct PlayerPowersText;
 pub struct PlayerPointsText;
 #[derive(Component)]
 pub struct PlayerGrazeText;
+#[derive(Component)]
+pub struct ScoreText;
+#[derive(Component)]
+pub struct HiScoreText;
+#[derive(Component)]
+pub struct DebugOverlayText;
+#[derive(Component)]
+pub struct BossHealthBarText;
+#[derive(Component)]
+pub struct BossTimerText;
 #[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[source(AppState = AppState::MainMenu)]
 enum MainMenuState {
@@ -29,6 +55,9 @@ enum MainMenuState {
     MusicRoom,
     Option,
     Quit,
+    NameEntry,
+    KeyConfig,
+    SpellPractice,
 }
 #[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[source(MainMenuState = MainMenuState::Start)]
@@ -60,6 +89,8 @@ enum PausedUiState {
     #[default]
     Choosing,
     Resume,
+    Retry,
+    SaveReplay,
     ReturnToTitle,
 }
 #[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
@@ -83,6 +114,143 @@ enum MarisaSpellCardState {
     SpellC,
     SpellD,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GameOverEntry {
+    Continue,
+    ReturnToTitle,
+    SaveReplay,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StageClearEntry {
+    ReturnToTitle,
+    SaveReplay,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OptionEntry {
+    BgmVolume,
+    SfxVolume,
+    Screen,
+    Resolution,
+    Vsync,
+    PracticeLives,
+    Hitbox,
+    Language,
+    Bloom,
+    DeadZone,
+    StickSnap,
+    KeyConfig,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PracticeEntry {
+    Difficulty,
+    Character,
+    SpellCard,
+    Stage,
+    Lives,
+    Bombs,
+    Power,
+    Start,
+    SpellPractice,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExtraEntry {
+    Character,
+    SpellCard,
+    Start,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KeyCapture {
+    Idle,
+    /// the next key pressed is bound to the selected action
+    Waiting,
+    /// a key was bound or the capture cancelled this update, its press must not reach the menu
+    Bound,
+}
+#[derive(Event)]
+pub struct GameContinued;
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NameEntryCell {
+    Char(char),
+    Space,
+    Delete,
+    End,
+}
+#[derive(Resource)]
+struct NameEntry {
+    name: String,
+    row: usize,
+    column: usize,
+    vertical_timer: Timer,
+    horizontal_timer: Timer,
+}
+#[derive(Resource)]
+struct ReplayList {
+    replays: Vec<(String, ReplayFile)>,
+    selected: usize,
+    repeat_timer: Timer,
+}
+#[derive(Resource)]
+struct MusicRoom {
+    selected: usize,
+    repeat_timer: Timer,
+    /// track picked for playback, decoded in the background for the spectrum
+    decoding: Option<(String, Task<DecodedTrack>)>,
+    decoded: Option<(String, DecodedTrack)>,
+    levels: Vec<f32>,
+}
+#[derive(Resource)]
+struct SelectedOption {
+    selected: OptionEntry,
+    repeat_timer: Timer,
+    horizontal_timer: Timer,
+}
+/// kept from one practice run to the next until the title screen is entered again
+#[derive(Resource)]
+struct PracticeMenu {
+    selected: PracticeEntry,
+    repeat_timer: Timer,
+    horizontal_timer: Timer,
+    difficulty: Difficulty,
+    character: Character,
+    spell_card: SpellCard,
+    stage: usize,
+    lives: i32,
+    bombs: i32,
+    power: i32,
+    /// index into the spell practice list
+    spell_practice_selected: usize,
+}
+#[derive(Resource)]
+struct ExtraMenu {
+    selected: ExtraEntry,
+    repeat_timer: Timer,
+    horizontal_timer: Timer,
+    character: Character,
+    spell_card: SpellCard,
+}
+#[derive(Resource)]
+struct KeyConfigScreen {
+    /// index into `Action::ALL`, one past the end is the reset entry
+    selected: usize,
+    repeat_timer: Timer,
+    capture: KeyCapture,
+    message: String,
+}
+#[derive(Resource)]
+struct SelectedScorePage {
+    page: usize,
+    repeat_timer: Timer,
+}
+#[derive(Resource)]
+struct SelectedGameOverEntry {
+    selected: GameOverEntry,
+    repeat_timer: Timer,
+}
+#[derive(Resource)]
+struct SelectedStageClearEntry {
+    selected: StageClearEntry,
+    repeat_timer: Timer,
+}
 #[derive(Resource)]
 struct SelectedConfirmReturnToTitleEntry {
     selected: ConfirmReturnToTitleState,
@@ -139,32 +307,737 @@ struct CharacterEntry(CharacterState);
 struct PausedEntry(PausedUiState);
 #[derive(Component)]
 struct ConfirmReturnToTitleEntry(ConfirmReturnToTitleState);
+#[derive(Component)]
+struct GameOverMenuEntry(GameOverEntry);
+#[derive(Component)]
+struct StageClearMenuEntry(StageClearEntry);
+#[derive(Component)]
+struct ScorePageText;
+#[derive(Component)]
+struct NameEntryText;
+#[derive(Component)]
+struct ReplayListText;
+#[derive(Component)]
+struct MusicRoomText;
+#[derive(Component)]
+struct OptionText;
+#[derive(Component)]
+struct KeyConfigText;
+#[derive(Component)]
+struct PracticeText;
+#[derive(Component)]
+struct ExtraText;
+#[derive(Component)]
+struct SpellPracticeText;
+#[derive(Component)]
+struct SpectrumText;
+#[derive(Component)]
+struct DialogueSpeakerText;
+#[derive(Component)]
+struct DialogueLineText;
+#[derive(Component)]
+struct DialoguePortrait {
+    side: PortraitSide,
+    speaker: String,
+    clip: String,
+}
+#[derive(Component)]
+struct FadeOverlay;
 fn cleanup_confirm_return_to_title(
     mut commands: Commands,
-    confirm_container: Query<Entity, With<ConfirmReturnToTitleContainer>>,
+    confirm_container: Query<Entity, With<ConfirmReturnToTitleContainer>>,
+) {
+    if let Ok(entity) = confirm_container.get_single() {
+        commands.entity(entity).despawn_descendants();
+    }
+}
+fn setup_paused(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+    practice: Option<Res<PracticeRun>>,
+    recorder: Option<Res<ReplayRecorder>>,
+) {
+    let font_size = 40.0;
+    let text_font = TextFont {
+        font: font.0.clone(),
+        font_size,
+        ..default()
+    };
+
+    commands.insert_resource(SelectedPauseEntry {
+        selected: PausedUiState::Resume,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+    });
+
+    commands.spawn((
+        StateScoped(GameState::Paused),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                PausedContainer,
+            ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Paused"),
+                        text_font.clone(),
+                        TextLayout::new_with_justify(JustifyText::Center),
+                        TextColor(Color::Srgba(WHITE)),
+                    ));
+
+                    let mut pause_entries = vec![
+                        (PausedUiState::Resume, "  Resume"),
+                        (PausedUiState::ReturnToTitle, "  Return to Title")
+                    ];
+                    if practice.is_some() {
+                        pause_entries.insert(1, (PausedUiState::Retry, "  Retry"));
+                    }
+                    // quitting from here is the last chance to keep the run
+                    if recorder.is_some() {
+                        pause_entries.insert(1, (PausedUiState::SaveReplay, "  Save Replay"));
+                    }
+                    for (entry, label) in pause_entries.into_iter() {
+                        parent.spawn((
+                            Text::new(label),
+                            text_font.clone(),
+                            TextLayout::new_with_justify(JustifyText::Left),
+                            TextColor(Color::Srgba(WHITE)),
+                            PausedEntry(entry),
+                        ));
+                    }
+
+                    parent.spawn((
+                        Node {
+                            position_type: PositionType::Absolute,
+                            flex_direction: FlexDirection::Column,
+                            margin: UiRect {
+                                top: Val::Px(font_size * 4.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        ConfirmReturnToTitleContainer,
+                    ));
+                });
+        });
+}
+
+fn setup_game_over(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+) {
+    let font_size = 40.0;
+    let text_font = TextFont {
+        font: font.0.clone(),
+        font_size,
+        ..default()
+    };
+    let banner = [
+        r"  ___   _   __  __ ___    _____   _____ ___ ",
+        r" / __| /_\ |  \/  | __|  / _ \ \ / / __| _ \",
+        r"| (_ |/ _ \| |\/| | _|  | (_) \ V /| _||   /",
+        r" \___/_/ \_\_|  |_|___|  \___/ \_/ |___|_|_\",
+    ].join("\n");
+
+    commands.insert_resource(SelectedGameOverEntry {
+        selected: GameOverEntry::Continue,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+    });
+
+    commands.spawn((
+        StateScoped(GameState::GameOver),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Column,
+                ..default()
+            })
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(banner),
+                        TextFont {
+                            font: font.0.clone(),
+                            font_size: font_size * 0.6,
+                            ..default()
+                        },
+                        TextLayout::new_with_justify(JustifyText::Left),
+                        TextColor(Color::Srgba(RED)),
+                    ));
+
+                    let entries = vec![
+                        (GameOverEntry::Continue, "  Continue"),
+                        (GameOverEntry::ReturnToTitle, "  Return to Title"),
+                        (GameOverEntry::SaveReplay, "  Save Replay"),
+                    ];
+                    for (entry, label) in entries.into_iter() {
+                        parent.spawn((
+                            Text::new(label),
+                            text_font.clone(),
+                            TextLayout::new_with_justify(JustifyText::Left),
+                            TextColor(Color::Srgba(WHITE)),
+                            GameOverMenuEntry(entry),
+                        ));
+                    }
+                });
+        });
+}
+
+
+fn setup_stage_clear(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+) {
+    let font_size = 40.0;
+    let text_font = TextFont {
+        font: font.0.clone(),
+        font_size,
+        ..default()
+    };
+
+    commands.insert_resource(SelectedStageClearEntry {
+        selected: StageClearEntry::ReturnToTitle,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+    });
+
+    commands.spawn((
+        StateScoped(GameState::StageClear),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Column,
+                ..default()
+            })
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Stage Clear"),
+                        text_font.clone(),
+                        TextLayout::new_with_justify(JustifyText::Center),
+                        TextColor(Color::Srgba(YELLOW)),
+                    ));
+
+                    let entries = vec![
+                        (StageClearEntry::ReturnToTitle, "  Return to Title"),
+                        (StageClearEntry::SaveReplay, "  Save Replay"),
+                    ];
+                    for (entry, label) in entries.into_iter() {
+                        parent.spawn((
+                            Text::new(label),
+                            text_font.clone(),
+                            TextLayout::new_with_justify(JustifyText::Left),
+                            TextColor(Color::Srgba(WHITE)),
+                            StageClearMenuEntry(entry),
+                        ));
+                    }
+                });
+        });
+}
+fn setup_fade_overlay(
+    mut commands: Commands,
+    window: Res<WindowSize>,
+) {
+    commands.spawn((
+        StateScoped(AppState::InGame),
+        FadeOverlay,
+        Sprite {
+            color: Color::BLACK.with_alpha(0.0),
+            custom_size: Some(Vec2::new(window.width, window.height)),
+            ..default()
+        },
+        Transform::from_translation(Vec3::new(0.0, 0.0, 10.0)),
+    ));
+}
+
+fn update_fade_overlay(
+    fade: Res<ScreenFade>,
+    mut overlay: Query<&mut Sprite, With<FadeOverlay>>,
+) {
+    for mut sprite in overlay.iter_mut() {
+        sprite.color = Color::BLACK.with_alpha(fade.alpha);
+    }
+}
+
+fn setup_dialogue(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+) {
+    const COLUMNS: usize = 50;
+    const ROWS: usize = 5;
+    const FONT_SIZE: f32 = 24.0;
+    // the box sits at the bottom of the playfield, above the fade
+    const CENTER: Vec2 = Vec2::new(-220.0, -220.0);
+
+    let char_width = FONT_SIZE * 0.6;
+    let line_height = FONT_SIZE * 1.2;
+    let size = Vec2::new(COLUMNS as f32 * char_width, (ROWS + 2) as f32 * line_height);
+    let top_left = CENTER + Vec2::new(-size.x / 2.0 + 2.0 * char_width, size.y / 2.0 - line_height);
+    let text_font = TextFont {
+        font: font.0.clone(),
+        font_size: FONT_SIZE,
+        ..default()
+    };
+
+    let border = format!("+{}+", "-".repeat(COLUMNS - 2));
+    let side = format!("|{}|", " ".repeat(COLUMNS - 2));
+    let frame = std::iter::once(border.clone())
+        .chain(std::iter::repeat_n(side, ROWS))
+        .chain(std::iter::once(border))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    commands.spawn((
+        StateScoped(GameState::Dialogue),
+        Sprite {
+            color: Color::BLACK.with_alpha(0.85),
+            custom_size: Some(size),
+            ..default()
+        },
+        Transform::from_translation(CENTER.extend(11.0)),
+    ));
+    commands.spawn((
+        StateScoped(GameState::Dialogue),
+        Text2d::new(frame),
+        text_font.clone(),
+        TextLayout::default(),
+        TextColor(Color::Srgba(GRAY)),
+        Transform::from_translation(CENTER.extend(12.0)),
+    ));
+    commands.spawn((
+        StateScoped(GameState::Dialogue),
+        DialogueSpeakerText,
+        Text2d::new(""),
+        text_font.clone(),
+        TextLayout::new_with_justify(JustifyText::Left),
+        TextColor(Color::Srgba(YELLOW)),
+        Anchor::TopLeft,
+        Transform::from_translation(top_left.extend(12.0)),
+    ));
+    commands.spawn((
+        StateScoped(GameState::Dialogue),
+        DialogueLineText,
+        Text2d::new(""),
+        text_font,
+        TextLayout::new_with_justify(JustifyText::Left),
+        TextBounds::new_horizontal((COLUMNS - 4) as f32 * char_width),
+        TextColor(Color::Srgba(WHITE)),
+        Anchor::TopLeft,
+        Transform::from_translation((top_left - Vec2::new(0.0, line_height)).extend(12.0)),
+    ));
+}
+
+fn dialogue_update_texts(
+    active: Res<ActiveDialogue>,
+    dialogues: Res<Assets<DialogueAsset>>,
+    mut speaker_text: Query<&mut Text2d, (With<DialogueSpeakerText>, Without<DialogueLineText>)>,
+    mut line_text: Query<(&mut Text2d, &mut TextColor), With<DialogueLineText>>,
+) {
+    if !active.is_changed() {
+        return;
+    }
+    let Some(line) = dialogues.get(&active.dialogue).and_then(|dialogue| dialogue.lines.get(active.line)) else {
+        return;
+    };
+    if let Ok(mut text) = speaker_text.get_single_mut() {
+        text.0 = line.speaker.clone().unwrap_or_default();
+    }
+    if let Ok((mut text, mut color)) = line_text.get_single_mut() {
+        text.0 = line.text.clone();
+        // narration reads like the stage directions of the script
+        color.0 = if line.speaker.is_some() { Color::Srgba(WHITE) } else { Color::Srgba(GRAY) };
+    }
+}
+
+/// portraits of the speakers so far, one per side, the one speaking is lit and the other dimmed
+fn dialogue_portraits(
+    mut commands: Commands,
+    active: Res<ActiveDialogue>,
+    dialogues: Res<Assets<DialogueAsset>>,
+    portraits: Portraits,
+    font: Res<AsciiFont>,
+    mut shown: Query<(Entity, &DialoguePortrait, &mut AsciiAnimation)>,
+) {
+    const FONT_SIZE: f32 = 20.0;
+    const DIMMED: f32 = 0.35;
+
+    let Some(library) = portraits.library() else {
+        return;
+    };
+    let speaker = dialogues
+        .get(&active.dialogue)
+        .and_then(|dialogue| dialogue.lines.get(active.line))
+        .and_then(|line| line.speaker.as_deref());
+
+    for side in [PortraitSide::Left, PortraitSide::Right] {
+        // a later arrival takes the side over
+        let wanted = active.expressions
+            .iter()
+            .rev()
+            .filter_map(|(name, clip)| Some((library.portrait(name)?, clip)))
+            .find(|(portrait, _)| portrait.side == side);
+        let current = shown.iter_mut().find(|(_, portrait, _)| portrait.side == side);
+
+        let up_to_date = match (&wanted, &current) {
+            (Some((portrait, clip)), Some((_, shown, _))) => shown.speaker == portrait.speaker && shown.clip == **clip,
+            (None, None) => true,
+            _ => false,
+        };
+        if up_to_date {
+            if let Some((_, shown, mut animation)) = current {
+                animation.set_brightness(if speaker == Some(shown.speaker.as_str()) { 1.0 } else { DIMMED });
+            }
+            continue;
+        }
+
+        // keep the old clip up until the new one has loaded
+        let animation = match wanted {
+            Some((portrait, clip)) => match portraits.animation(portrait, clip) {
+                Some(animation) => Some((portrait, clip, animation)),
+                None => continue,
+            },
+            None => None,
+        };
+        if let Some((entity, _, _)) = current {
+            commands.entity(entity).despawn_recursive();
+        }
+        if let Some((portrait, clip, animation)) = animation {
+            let position = match side {
+                PortraitSide::Left => Vec3::new(-500.0, -30.0, 11.0),
+                PortraitSide::Right => Vec3::new(60.0, -30.0, 11.0),
+            };
+            let entity = spawn_ascii_animation(&mut commands, animation, &font.0, FONT_SIZE, Transform::from_translation(position));
+            commands.entity(entity).insert((
+                StateScoped(GameState::Dialogue),
+                DialoguePortrait {
+                    side,
+                    speaker: portrait.speaker.clone(),
+                    clip: clip.clone(),
+                },
+            ));
+        }
+    }
+}
+
+fn score_pages() -> Vec<(Difficulty, Character)> {
+    let difficulties = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Lunatic, Difficulty::Extra];
+    let characters = [Character::ReimuHakurei, Character::MarisaKirisame];
+    difficulties
+        .iter()
+        .flat_map(|difficulty| characters.iter().map(move |character| (*difficulty, *character)))
+        .collect()
+}
+
+fn setup_score(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+) {
+    let font_size = 30.0;
+
+    commands.insert_resource(SelectedScorePage {
+        page: 0,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+    });
+
+    commands.spawn((
+        StateScoped(MainMenuState::Score),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                ScorePageText,
+            ));
+        });
+}
+
+fn setup_replay(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+    save_dir: Res<SaveDir>,
+) {
+    let font_size = 30.0;
+
+    commands.insert_resource(ReplayList {
+        replays: list_replays(&save_dir),
+        selected: 0,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+    });
+
+    commands.spawn((
+        StateScoped(MainMenuState::Replay),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                ReplayListText,
+            ));
+        });
+}
+
+fn setup_music_room(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+) {
+    let font_size = 26.0;
+
+    commands.insert_resource(MusicRoom {
+        selected: 0,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+        decoding: None,
+        decoded: None,
+        levels: Vec::new(),
+    });
+
+    commands.spawn((
+        StateScoped(MainMenuState::MusicRoom),
+        Node {
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                MusicRoomText,
+            ));
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size: font_size * 0.75,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(LIME)),
+                Node {
+                    margin: UiRect::top(Val::Px(font_size)),
+                    ..default()
+                },
+                SpectrumText,
+            ));
+        });
+}
+
+fn setup_options(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+) {
+    let font_size = 30.0;
+
+    commands.insert_resource(SelectedOption {
+        selected: OptionEntry::BgmVolume,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+        horizontal_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+    });
+
+    commands.spawn((
+        StateScoped(MainMenuState::Option),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                OptionText,
+            ));
+        });
+}
+
+fn setup_practice(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+    mut menu: ResMut<PracticeMenu>,
+) {
+    let font_size = 30.0;
+    // the menu outlives this screen, redraw it on every visit
+    menu.set_changed();
+
+    commands.spawn((
+        StateScoped(MainMenuState::PracticeStart),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                PracticeText,
+            ));
+        });
+}
+
+fn setup_extra(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+    clear_records: Res<ClearRecords>,
+) {
+    let font_size = 30.0;
+    let character = clear_records.cleared.first().copied().unwrap_or_default();
+
+    commands.insert_resource(ExtraMenu {
+        selected: ExtraEntry::Start,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+        horizontal_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+        character,
+        spell_card: character_spell_cards(character)[0],
+    });
+
+    commands.spawn((
+        StateScoped(MainMenuState::ExtraStart),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                ExtraText,
+            ));
+        });
+}
+
+fn setup_spell_practice(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+    mut menu: ResMut<PracticeMenu>,
 ) {
-    if let Ok(entity) = confirm_container.get_single() {
-        commands.entity(entity).despawn_descendants();
-    }
+    let font_size = 30.0;
+    menu.set_changed();
+
+    commands.spawn((
+        StateScoped(MainMenuState::SpellPractice),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                SpellPracticeText,
+            ));
+        });
 }
-fn setup_paused(
+
+fn setup_key_config(
     mut commands: Commands,
     font: Res<AsciiFont>,
 ) {
-    let font_size = 40.0;
-    let text_font = TextFont {
-        font: font.0.clone(),
-        font_size,
-        ..default()
-    };
+    let font_size = 30.0;
 
-    commands.insert_resource(SelectedPauseEntry {
-        selected: PausedUiState::Resume,
+    commands.insert_resource(KeyConfigScreen {
+        selected: 0,
         repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+        capture: KeyCapture::Idle,
+        message: String::new(),
     });
 
     commands.spawn((
-        StateScoped(GameState::Paused),
+        StateScoped(MainMenuState::KeyConfig),
         Node {
             justify_content: JustifyContent::Center,
             align_items: AlignItems::Center,
@@ -175,47 +1048,73 @@ fn setup_paused(
     ))
         .with_children(|parent| {
             parent.spawn((
-                Node {
-                    flex_direction: FlexDirection::Column,
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
                     ..default()
                 },
-                PausedContainer,
-            ))
-                .with_children(|parent| {
-                    parent.spawn((
-                        Text::new("Paused"),
-                        text_font.clone(),
-                        TextLayout::new_with_justify(JustifyText::Center),
-                        TextColor(Color::Srgba(WHITE)),
-                    ));
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                KeyConfigText,
+            ));
+        });
+}
 
-                    let pause_entries = vec![
-                        (PausedUiState::Resume, "  Resume"),
-                        (PausedUiState::ReturnToTitle, "  Return to Title")
-                    ];
-                    for (entry, label) in pause_entries.into_iter() {
-                        parent.spawn((
-                            Text::new(label),
-                            text_font.clone(),
-                            TextLayout::new_with_justify(JustifyText::Left),
-                            TextColor(Color::Srgba(WHITE)),
-                            PausedEntry(entry),
-                        ));
-                    }
+const MAX_NAME_LENGTH: usize = 8;
+fn name_entry_grid() -> Vec<Vec<NameEntryCell>> {
+    let rows = [
+        "ABCDEFGHIJKLM",
+        "NOPQRSTUVWXYZ",
+        "abcdefghijklm",
+        "nopqrstuvwxyz",
+        "0123456789.-!",
+        "?#$%&*+/:;=@~",
+    ];
+    let mut grid: Vec<Vec<NameEntryCell>> = rows
+        .iter()
+        .map(|row| row.chars().map(NameEntryCell::Char).collect())
+        .collect();
+    grid.push(vec![NameEntryCell::Space, NameEntryCell::Delete, NameEntryCell::End]);
+    grid
+}
 
-                    parent.spawn((
-                        Node {
-                            position_type: PositionType::Absolute,
-                            flex_direction: FlexDirection::Column,
-                            margin: UiRect {
-                                top: Val::Px(font_size * 4.0),
-                                ..default()
-                            },
-                            ..default()
-                        },
-                        ConfirmReturnToTitleContainer,
-                    ));
-                });
+fn setup_name_entry(
+    mut commands: Commands,
+    font: Res<AsciiFont>,
+) {
+    let font_size = 30.0;
+
+    commands.insert_resource(NameEntry {
+        name: String::new(),
+        row: 0,
+        column: 0,
+        vertical_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+        horizontal_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+    });
+
+    commands.spawn((
+        StateScoped(MainMenuState::NameEntry),
+        Node {
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Left),
+                TextColor(Color::Srgba(WHITE)),
+                NameEntryText,
+            ));
         });
 }
 
@@ -475,21 +1374,462 @@ fn paused_update_texts(
         }
     }
 }
-fn confirm_return_to_title_update_texts(
-    selected: Res<SelectedConfirmReturnToTitleEntry>,
-    mut texts: Query<(&ConfirmReturnToTitleEntry, &mut Text)>,
+fn confirm_return_to_title_update_texts(
+    selected: Res<SelectedConfirmReturnToTitleEntry>,
+    mut texts: Query<(&ConfirmReturnToTitleEntry, &mut Text)>,
+) {
+    if !selected.is_changed() {
+        return;
+    }
+
+    for (entry, mut text) in texts.iter_mut() {
+        let label = text.0.trim_start_matches(['>', ' ']);
+        if entry.0 == selected.selected {
+            text.0 = format!("> {}", label);
+        } else {
+            text.0 = format!("  {}", label);
+        }
+    }
+}
+fn game_over_update_texts(
+    selected: Res<SelectedGameOverEntry>,
+    mut texts: Query<(&GameOverMenuEntry, &mut Text)>,
+) {
+    if !selected.is_changed() {
+        return;
+    }
+
+    for (entry, mut text) in texts.iter_mut() {
+        let label = text.0.trim_start_matches(['>', ' ']);
+        if entry.0 == selected.selected {
+            text.0 = format!("> {}", label);
+        } else {
+            text.0 = format!("  {}", label);
+        }
+    }
+}
+fn stage_clear_update_texts(
+    selected: Res<SelectedStageClearEntry>,
+    mut texts: Query<(&StageClearMenuEntry, &mut Text)>,
+) {
+    if !selected.is_changed() {
+        return;
+    }
+
+    for (entry, mut text) in texts.iter_mut() {
+        let label = text.0.trim_start_matches(['>', ' ']);
+        if entry.0 == selected.selected {
+            text.0 = format!("> {}", label);
+        } else {
+            text.0 = format!("  {}", label);
+        }
+    }
+}
+fn score_update_texts(
+    selected: Res<SelectedScorePage>,
+    high_scores: Res<HighScores>,
+    mut texts: Query<&mut Text, With<ScorePageText>>,
+) {
+    if !selected.is_changed() {
+        return;
+    }
+
+    let pages = score_pages();
+    let (difficulty, character) = pages[selected.page % pages.len()];
+    let entries = high_scores.table(difficulty, character);
+
+    let mut lines = vec![
+        format!("Score   < {:?} / {} >   {}/{}", difficulty, character.def().name, selected.page + 1, pages.len()),
+        String::new(),
+    ];
+    for rank in 0..HIGH_SCORE_ENTRIES {
+        lines.push(match entries.get(rank) {
+            Some(entry) => format!(
+                "{:>2}. {:<8} {:>10}  {}  {}  {:>5.1}%",
+                rank + 1, entry.name, entry.score,
+                if difficulty == Difficulty::Extra { "Extra  ".to_string() } else { format!("Stage {}", entry.stage) },
+                entry.date, entry.slowdown,
+            ),
+            None => format!("{:>2}. {:<8} {:>10}", rank + 1, "--------", 0),
+        });
+    }
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn replay_update_texts(
+    list: Res<ReplayList>,
+    mut texts: Query<&mut Text, With<ReplayListText>>,
+) {
+    if !list.is_changed() {
+        return;
+    }
+
+    let mut lines = vec![
+        "Replay".to_string(),
+        String::new(),
+    ];
+    if list.replays.is_empty() {
+        lines.push("No replays saved".to_string());
+    }
+    for (index, (_, replay)) in list.replays.iter().enumerate() {
+        let seconds = replay.duration_secs();
+        let cursor = if index == list.selected { ">" } else { " " };
+        lines.push(format!(
+            "{} No.{:02}  {}  {:<16} {:<8} {:02}:{:02}",
+            cursor, index + 1, replay.date, replay.character.def().name,
+            format!("{:?}", replay.difficulty), seconds / 60, seconds % 60,
+        ));
+    }
+    lines.push(String::new());
+    lines.push("Hold Ctrl to fast-forward, Esc to pause".to_string());
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn music_room_update_texts(
+    room: Res<MusicRoom>,
+    manager: Res<MusicManager>,
+    heard: Res<HeardTracks>,
+    library: Res<MusicLibrary>,
+    libraries: Res<Assets<MusicLibraryAsset>>,
+    mut texts: Query<&mut Text, With<MusicRoomText>>,
+) {
+    let Some(library) = libraries.get(&library.0) else {
+        return;
+    };
+
+    let mut lines = vec![
+        "Music Room".to_string(),
+        String::new(),
+    ];
+    for (index, track) in library.tracks.iter().enumerate() {
+        let cursor = if index == room.selected { ">" } else { " " };
+        let title = if heard.contains(&track.id) { track.title.clone() } else { "?".repeat(track.title.chars().count()) };
+        let playing = if manager.current.as_deref() == Some(track.id.as_str()) { "  <- now playing" } else { "" };
+        lines.push(format!("{} No.{:02}  {}{}", cursor, index + 1, title, playing));
+    }
+    lines.push(String::new());
+    match library.tracks.get(room.selected) {
+        Some(track) if heard.contains(&track.id) => lines.extend(track.comment.lines().map(str::to_string)),
+        Some(_) => lines.push("Not heard yet, play on to unlock this track.".to_string()),
+        None => {}
+    }
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn music_room_spectrum(
+    time: Res<Time>,
+    mut room: ResMut<MusicRoom>,
+    manager: Res<MusicManager>,
+    mut texts: Query<&mut Text, With<SpectrumText>>,
+) {
+    const BANDS: usize = 32;
+    const ROWS: usize = 8;
+    const DECAY_PER_SEC: f32 = 1.5;
+
+    let room = &mut *room;
+    if let Some((id, task)) = room.decoding.as_mut()
+        && let Some(decoded) = block_on(future::poll_once(task))
+    {
+        room.decoded = Some((id.clone(), decoded));
+        room.decoding = None;
+    }
+
+    let spectrum = match &room.decoded {
+        Some((id, decoded)) if manager.current.as_deref() == Some(id.as_str()) => decoded.spectrum(manager.elapsed, BANDS),
+        _ => vec![0.0; BANDS],
+    };
+    // bars jump up at once and fall back slowly
+    room.levels.resize(BANDS, 0.0);
+    for (level, target) in room.levels.iter_mut().zip(spectrum) {
+        *level = target.max(*level - DECAY_PER_SEC * time.delta_secs());
+    }
+
+    let lines: Vec<String> = (0..ROWS).rev().map(|row| {
+        room.levels.iter().map(|level| {
+            let fill = level * ROWS as f32 - row as f32;
+            if fill >= 1.0 {
+                "## "
+            } else if fill >= 0.5 {
+                "== "
+            } else if row == 0 {
+                "__ "
+            } else {
+                "   "
+            }
+        }).collect()
+    }).collect();
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn option_label(entry: OptionEntry, config: &Config) -> (&'static str, String) {
+    let on_off = |on: bool| if on { "On" } else { "Off" }.to_string();
+    match entry {
+        OptionEntry::BgmVolume => ("BGM Volume", format!("{:.0}%", config.bgm_volume * 100.0)),
+        OptionEntry::SfxVolume => ("SFX Volume", format!("{:.0}%", config.sfx_volume * 100.0)),
+        OptionEntry::Screen => ("Screen", if config.fullscreen { "Fullscreen" } else { "Windowed" }.to_string()),
+        OptionEntry::Resolution => ("Resolution", format!("{}x{}", config.resolution.0, config.resolution.1)),
+        OptionEntry::Vsync => ("VSync", on_off(config.vsync)),
+        OptionEntry::PracticeLives => ("Practice Lives", config.practice_lives.to_string()),
+        OptionEntry::Hitbox => ("Hitbox", if config.always_show_hitbox { "Always" } else { "Focus" }.to_string()),
+        OptionEntry::Language => ("Language", config.language.name().to_string()),
+        OptionEntry::Bloom => ("Bloom", on_off(config.bloom)),
+        OptionEntry::DeadZone => ("Stick Dead Zone", format!("{:.0}%", config.stick_dead_zone * 100.0)),
+        OptionEntry::StickSnap => ("Stick 8-Way Snap", on_off(config.stick_snap)),
+        OptionEntry::KeyConfig => ("Key Config", String::new()),
+    }
+}
+/// bombs a character can pick, in the order of the start menu
+fn character_spell_cards(character: Character) -> [SpellCard; 2] {
+    match character {
+        Character::ReimuHakurei => [SpellCard::FantasyOrb, SpellCard::HomingAmulet],
+        Character::MarisaKirisame => [SpellCard::MasterSpark, SpellCard::StardustReverie],
+    }
+}
+fn spell_card_name(spell_card: SpellCard) -> &'static str {
+    match spell_card {
+        SpellCard::FantasyOrb => "Fantasy Orb",
+        SpellCard::HomingAmulet => "Homing Amulet",
+        SpellCard::MasterSpark => "Master Spark",
+        SpellCard::StardustReverie => "Stardust Reverie",
+    }
+}
+/// loaded stages practice may start, every stage up to the furthest one reached in a regular run
+fn practice_stages<'a>(stage_list: &StageList, stage_assets: &'a Assets<StageAsset>, records: &PracticeRecords) -> Vec<&'a StageAsset> {
+    stage_list.stages
+        .iter()
+        .filter_map(|handle| stage_assets.get(handle))
+        .take(records.reached_stage + 1)
+        .collect()
+}
+fn practice_label(entry: PracticeEntry, menu: &PracticeMenu, stages: &[&StageAsset]) -> (&'static str, String) {
+    match entry {
+        PracticeEntry::Difficulty => ("Difficulty", format!("{:?}", menu.difficulty)),
+        PracticeEntry::Character => ("Character", menu.character.def().name.to_string()),
+        PracticeEntry::SpellCard => ("Bomb", spell_card_name(menu.spell_card).to_string()),
+        PracticeEntry::Stage => ("Stage", stages.get(menu.stage).map(|stage| stage.name.clone()).unwrap_or_default()),
+        PracticeEntry::Lives => ("Lives", menu.lives.to_string()),
+        PracticeEntry::Bombs => ("Bombs", menu.bombs.to_string()),
+        PracticeEntry::Power => ("Power", menu.power.to_string()),
+        PracticeEntry::Start => ("Start", String::new()),
+        PracticeEntry::SpellPractice => ("Spell Practice", String::new()),
+    }
+}
+fn practice_update_texts(
+    menu: Res<PracticeMenu>,
+    records: Res<PracticeRecords>,
+    stage_list: Res<StageList>,
+    stage_assets: Res<Assets<StageAsset>>,
+    mut texts: Query<&mut Text, With<PracticeText>>,
+) {
+    use PracticeEntry::*;
+    if !menu.is_changed() {
+        return;
+    }
+
+    let stages = practice_stages(&stage_list, &stage_assets, &records);
+    let mut lines = vec![
+        "Practice Start".to_string(),
+        String::new(),
+    ];
+    for entry in [Difficulty, Character, SpellCard, Stage, Lives, Bombs, Power, Start, SpellPractice] {
+        let cursor = if entry == menu.selected { ">" } else { " " };
+        let (label, value) = practice_label(entry, &menu, &stages);
+        if matches!(entry, Start | SpellPractice) {
+            lines.push(format!("{} {}", cursor, label));
+        } else {
+            lines.push(format!("{} {:<16} < {} >", cursor, label, value));
+        }
+    }
+    lines.push(String::new());
+    lines.push("Clear a stage in a regular run to practice the next one".to_string());
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn extra_update_texts(
+    menu: Res<ExtraMenu>,
+    clear_records: Res<ClearRecords>,
+    mut texts: Query<&mut Text, With<ExtraText>>,
+) {
+    use ExtraEntry::*;
+    if !menu.is_changed() {
+        return;
+    }
+
+    let mut lines = vec![
+        "Extra Start".to_string(),
+        String::new(),
+    ];
+    for entry in [Character, SpellCard, Start] {
+        let cursor = if entry == menu.selected { ">" } else { " " };
+        lines.push(match entry {
+            Character => format!("{} {:<16} < {} >", cursor, "Character", menu.character.def().name),
+            SpellCard => format!("{} {:<16} < {} >", cursor, "Bomb", spell_card_name(menu.spell_card)),
+            Start => format!("{} Start", cursor),
+        });
+    }
+    lines.push(String::new());
+    if !clear_records.is_cleared(menu.character) {
+        lines.push(format!("Locked: clear the game with {} to unlock", menu.character.def().name));
+    }
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn spell_practice_update_texts(
+    menu: Res<PracticeMenu>,
+    records: Res<PracticeRecords>,
+    stage_list: Res<StageList>,
+    stage_assets: Res<Assets<StageAsset>>,
+    mut texts: Query<&mut Text, With<SpellPracticeText>>,
+) {
+    if !menu.is_changed() {
+        return;
+    }
+
+    let stages = practice_stages(&stage_list, &stage_assets, &records);
+    let spell_cards = practice_spell_cards(stages.iter().copied());
+    let mut lines = vec![
+        format!("Spell Practice   {:?} / {}", menu.difficulty, menu.character.def().name),
+        String::new(),
+    ];
+    if spell_cards.is_empty() {
+        lines.push("No spell cards in the stages reached so far".to_string());
+    }
+    for (index, spell_card) in spell_cards.iter().enumerate() {
+        let cursor = if index == menu.spell_practice_selected { ">" } else { " " };
+        let line = match records.spell_card(&spell_card.name, menu.difficulty, menu.character) {
+            Some(record) if record.attempts > 0 => format!(
+                "{} No.{:02}  {:<10} {:<28} {:>3}/{:<3}",
+                cursor, index + 1, spell_card.boss_name, spell_card.name, record.captures, record.attempts,
+            ),
+            _ => format!(
+                "{} No.{:02}  {:<10} {:<28} {:>3}/{:<3}",
+                cursor, index + 1, "?".repeat(spell_card.boss_name.chars().count()),
+                "?".repeat(spell_card.name.chars().count()), 0, 0,
+            ),
+        };
+        lines.push(line);
+    }
+    lines.push(String::new());
+    lines.push("Cards met in a regular run or stage practice can be practiced here".to_string());
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn options_update_texts(
+    selected: Res<SelectedOption>,
+    config: Res<Config>,
+    mut texts: Query<&mut Text, With<OptionText>>,
+) {
+    use OptionEntry::*;
+    if !selected.is_changed() && !config.is_changed() {
+        return;
+    }
+
+    let mut lines = vec![
+        "Option".to_string(),
+        String::new(),
+    ];
+    for entry in [BgmVolume, SfxVolume, Screen, Resolution, Vsync, PracticeLives, Hitbox, Language, Bloom, DeadZone, StickSnap, KeyConfig] {
+        let cursor = if entry == selected.selected { ">" } else { " " };
+        let (label, value) = option_label(entry, &config);
+        if entry == KeyConfig {
+            lines.push(format!("{} {}", cursor, label));
+        } else {
+            lines.push(format!("{} {:<16} < {} >", cursor, label, value));
+        }
+    }
+    lines.push(String::new());
+    lines.push("Left/Right to change, X to save and go back".to_string());
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn key_config_update_texts(
+    screen: Res<KeyConfigScreen>,
+    bindings: Res<KeyBindings>,
+    mut texts: Query<&mut Text, With<KeyConfigText>>,
+) {
+    if !screen.is_changed() && !bindings.is_changed() {
+        return;
+    }
+
+    let mut lines = vec![
+        "Key Config".to_string(),
+        String::new(),
+    ];
+    for (index, action) in Action::ALL.iter().enumerate() {
+        let cursor = if index == screen.selected { ">" } else { " " };
+        let keys: Vec<String> = bindings.keys(*action).iter().map(|key| key_name(*key)).collect();
+        let keys = if screen.capture == KeyCapture::Waiting && index == screen.selected {
+            "Press a key, Esc to cancel".to_string()
+        } else {
+            keys.join(" / ")
+        };
+        lines.push(format!("{} {:<10} {}", cursor, action.name(), keys));
+    }
+    let cursor = if screen.selected == Action::ALL.len() { ">" } else { " " };
+    lines.push(format!("{} Reset to Default", cursor));
+    lines.push(String::new());
+    lines.push(screen.message.clone());
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+fn name_entry_update_texts(
+    entry: Res<NameEntry>,
+    pending: Option<Res<PendingHighScore>>,
+    mut texts: Query<&mut Text, With<NameEntryText>>,
 ) {
-    if !selected.is_changed() {
+    if !entry.is_changed() {
         return;
     }
+    let Some(pending) = pending else { return; };
+
+    let mut lines = vec![
+        "New High Score!".to_string(),
+        format!("{} / {:?}   Score {}", pending.character.def().name, pending.difficulty, pending.entry.score),
+        String::new(),
+        format!("Name: {}{}", entry.name, "_".repeat(MAX_NAME_LENGTH - entry.name.chars().count())),
+        String::new(),
+    ];
+    for (row_index, row) in name_entry_grid().iter().enumerate() {
+        let line: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(column_index, cell)| {
+                let label = match cell {
+                    NameEntryCell::Char(c) => c.to_string(),
+                    NameEntryCell::Space => "SP".to_string(),
+                    NameEntryCell::Delete => "DEL".to_string(),
+                    NameEntryCell::End => "END".to_string(),
+                };
+                if row_index == entry.row && column_index == entry.column {
+                    format!("[{}]", label)
+                } else {
+                    format!(" {} ", label)
+                }
+            })
+            .collect();
+        lines.push(line.join(""));
+    }
 
-    for (entry, mut text) in texts.iter_mut() {
-        let label = text.0.trim_start_matches(['>', ' ']);
-        if entry.0 == selected.selected {
-            text.0 = format!("> {}", label);
-        } else {
-            text.0 = format!("  {}", label);
-        }
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
     }
 }
 fn spell_card_update_texts(
@@ -528,20 +1868,22 @@ fn spell_card_update_texts(
 
 fn spell_card_selection(
     time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ButtonInput<Action>>,
     mut selected: ResMut<SelectedSpellCard>,
     selected_character: Res<SelectedCharacter>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
     let total = match selected_character.selected {
         CharacterState::ReimuHakurei => 2,
         CharacterState::MarisaKirisame => 2,
     };
 
-    let direction = navigation_direction(&keyboard_input, &mut selected.repeat_timer, &time.delta());
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
 
     if direction == 0 {
         return;
     }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
 
     selected.selected_index = ((selected.selected_index as isize + direction + total as isize) % total as isize) as usize;
 }
@@ -596,6 +1938,8 @@ fn setup_main_menu(
     bold_font: Res<AsciiBoldFont>,
     main_menu_animation: Res<MainMenuAnimation>,
     animation_assets: Res<Assets<AsciiAnimationAsset>>,
+    config: Res<Config>,
+    clear_records: Res<ClearRecords>,
 ) {
     // let animation_entity = spawn_ascii_animation(&mut commands, animation_assets.get(&main_menu_animation.0).unwrap(), &font.0, 40.0, Transform::from_translation(Vec3::ZERO));
     // commands.entity(animation_entity).insert(
@@ -621,6 +1965,20 @@ fn setup_main_menu(
         repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
     });
 
+    commands.insert_resource(PracticeMenu {
+        selected: PracticeEntry::Start,
+        repeat_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+        horizontal_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+        difficulty: Difficulty::Normal,
+        character: Character::ReimuHakurei,
+        spell_card: SpellCard::FantasyOrb,
+        stage: 0,
+        lives: config.practice_lives,
+        bombs: Difficulty::Normal.def().bombs,
+        power: 0,
+        spell_practice_selected: 0,
+    });
+
     let font_size = 40.0;
     let text_font = TextFont {
         font: font.0.clone(),
@@ -653,12 +2011,16 @@ fn setup_main_menu(
                .with_children(|parent| {
                    let menu_items = vec![
                        (MainMenuState::Start, "> Start"),
-                       // (MainMenuState::ExtraStart, "  Extra Start"),
-                       // (MainMenuState::PracticeStart, "  Practice Start"),
-                       // (MainMenuState::Replay, "  Replay"),
-                       // (MainMenuState::Score, "  Score"),
-                       // (MainMenuState::MusicRoom, "  Music Room"),
-                       // (MainMenuState::Option, "  Option"),
+                       (MainMenuState::ExtraStart, if clear_records.any_cleared() {
+                           "  Extra Start"
+                       } else {
+                           "  Extra Start  (clear the game to unlock)"
+                       }),
+                       (MainMenuState::PracticeStart, "  Practice Start"),
+                       (MainMenuState::Replay, "  Replay"),
+                       (MainMenuState::Score, "  Score"),
+                       (MainMenuState::MusicRoom, "  Music Room"),
+                       (MainMenuState::Option, "  Option"),
                        (MainMenuState::Quit, "  Quit"),
                    ];
 
@@ -669,12 +2031,13 @@ fn setup_main_menu(
                                ..default()
                            }
                        ).with_children(|parent| {
+                           let locked = *state == MainMenuState::ExtraStart && !clear_records.any_cleared();
                            parent.spawn((
                                MainMenuEntry(*state),
                                Text::new(*label),
                                text_font.clone(),
                                TextLayout::new_with_justify(JustifyText::Left),
-                               TextColor(Color::Srgba(WHITE)),
+                               TextColor(Color::Srgba(if locked { GRAY } else { WHITE })),
                            ));
                        });
                    }
@@ -757,6 +2120,26 @@ fn setup_in_game_ui(
         TextLayout::default(),
         TextColor(Color::Srgba(WHITE)),
 
+        Transform::from_translation(Vec3::new(info_margin, height / 2.0 * 0.25 + font_size * 3.0, 1.0)),
+        HiScoreText,
+    ));
+    commands.spawn((
+        StateScoped(AppState::InGame),
+        Text2d::new(""),
+        text_font.clone(),
+        TextLayout::default(),
+        TextColor(Color::Srgba(WHITE)),
+
+        Transform::from_translation(Vec3::new(info_margin, height / 2.0 * 0.25 + font_size * 1.5, 1.0)),
+        ScoreText,
+    ));
+    commands.spawn((
+        StateScoped(AppState::InGame),
+        Text2d::new(""),
+        text_font.clone(),
+        TextLayout::default(),
+        TextColor(Color::Srgba(WHITE)),
+
         Transform::from_translation(Vec3::new(info_margin, height / 2.0 * 0.25, 1.0)),
         PlayerLivesText,
     ));
@@ -801,6 +2184,44 @@ fn setup_in_game_ui(
         Transform::from_translation(Vec3::new(info_margin, height / 2.0 * 0.25 - font_size * 6.5, 1.0)),
         PlayerPointsText,
     ));
+
+    let playfield_center = (-width / 2.0 + width / 2.0 * 0.25) / 2.0;
+    let boss_font = TextFont {
+        font: font.0.clone(),
+        font_size: font_size * 0.6,
+        ..default()
+    };
+    commands.spawn((
+        StateScoped(AppState::InGame),
+        Text2d::new(""),
+        boss_font.clone(),
+        TextLayout::new_with_justify(JustifyText::Left),
+        TextColor(Color::Srgba(WHITE)),
+
+        Transform::from_translation(Vec3::new(playfield_center - font_size * 3.0, height / 2.0 - vertical_margin - font_size, 5.0)),
+        BossHealthBarText,
+    ));
+    commands.spawn((
+        StateScoped(AppState::InGame),
+        Text2d::new(""),
+        boss_font.clone(),
+        TextLayout::new_with_justify(JustifyText::Left),
+        TextColor(Color::Srgba(GRAY)),
+        Visibility::Hidden,
+
+        Transform::from_translation(Vec3::new(playfield_center, -height / 2.0 + vertical_margin + font_size, 5.0)),
+        DebugOverlayText,
+    ));
+    commands.spawn((
+        StateScoped(AppState::InGame),
+        Text2d::new(""),
+        boss_font,
+        TextLayout::new_with_justify(JustifyText::Right),
+        TextColor(Color::Srgba(WHITE)),
+
+        Transform::from_translation(Vec3::new(width / 2.0 * 0.25 - font_size * 2.0, height / 2.0 - vertical_margin - font_size, 5.0)),
+        BossTimerText,
+    ));
 }
 
 
@@ -818,96 +2239,511 @@ fn difficulty_update_texts(
     }
 }
 
-fn character_update_texts(
-    selected: Res<SelectedCharacter>,
-    mut texts: Query<(&CharacterEntry, &mut Text)>,
+fn character_update_texts(
+    selected: Res<SelectedCharacter>,
+    mut texts: Query<(&CharacterEntry, &mut Text)>,
+) {
+    for (entry, mut text) in texts.iter_mut() {
+        let label = text.0.trim_start_matches(['[', 'X', ']', ' ']);
+        if entry.0 == selected.selected {
+            text.0 = format!("[X] {}", label);
+        } else {
+            text.0 = format!("[ ] {}", label);
+        }
+    }
+}
+
+fn main_menu_update_texts(
+    selected: Res<SelectedMenuEntry>,
+    mut texts: Query<(&MainMenuEntry, &mut Text)>,
+) {
+    for (entry, mut text) in texts.iter_mut() {
+        let label = text.0.trim_start_matches(['>', ' ']);
+        if entry.0 == selected.selected {
+            text.0 = format!("> {}", label);
+        } else {
+            text.0 = format!("  {}", label);
+        }
+    }
+}
+
+fn navigation_direction(
+    actions: &ButtonInput<Action>,
+    timer: &mut Timer,
+    delta: &std::time::Duration,
+) -> isize {
+    let mut direction = 0;
+
+    if actions.just_pressed(Action::Up) {
+        direction = -1;
+        timer.reset();
+    } else if actions.just_pressed(Action::Down) {
+        direction = 1;
+        timer.reset();
+    } else if actions.pressed(Action::Up) {
+        timer.tick(*delta);
+        if timer.finished() {
+            direction = -1;
+        }
+    } else if actions.pressed(Action::Down) {
+        timer.tick(*delta);
+        if timer.finished() {
+            direction = 1;
+        }
+    } else {
+        timer.reset();
+    }
+
+    direction
+}
+
+fn horizontal_navigation_direction(
+    actions: &ButtonInput<Action>,
+    timer: &mut Timer,
+    delta: &std::time::Duration,
+) -> isize {
+    let mut direction = 0;
+
+    if actions.just_pressed(Action::Left) {
+        direction = -1;
+        timer.reset();
+    } else if actions.just_pressed(Action::Right) {
+        direction = 1;
+        timer.reset();
+    } else if actions.pressed(Action::Left) {
+        timer.tick(*delta);
+        if timer.finished() {
+            direction = -1;
+        }
+    } else if actions.pressed(Action::Right) {
+        timer.tick(*delta);
+        if timer.finished() {
+            direction = 1;
+        }
+    } else {
+        timer.reset();
+    }
+
+    direction
+}
+
+fn name_entry_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut entry: ResMut<NameEntry>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let grid = name_entry_grid();
+    let entry = &mut *entry;
+
+    let vertical = navigation_direction(&actions, &mut entry.vertical_timer, &time.delta());
+    let horizontal = horizontal_navigation_direction(&actions, &mut entry.horizontal_timer, &time.delta());
+    if vertical == 0 && horizontal == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    if vertical != 0 {
+        entry.row = ((entry.row as isize + vertical + grid.len() as isize) % grid.len() as isize) as usize;
+        entry.column = entry.column.min(grid[entry.row].len() - 1);
+    }
+    if horizontal != 0 {
+        let row_len = grid[entry.row].len() as isize;
+        entry.column = ((entry.column as isize + horizontal + row_len) % row_len) as usize;
+    }
+}
+
+fn paused_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut selected: ResMut<SelectedPauseEntry>,
+    practice: Option<Res<PracticeRun>>,
+    recorder: Option<Res<ReplayRecorder>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    use PausedUiState::*;
+    let order = match (practice, recorder) {
+        (Some(_), _) => vec![Resume, Retry, ReturnToTitle],
+        (None, Some(_)) => vec![Resume, SaveReplay, ReturnToTitle],
+        (None, None) => vec![Resume, ReturnToTitle],
+    };
+
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
+    if direction == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    let current_index = order.iter().position(|s| *s == selected.selected).unwrap_or(0);
+    let new_index = (current_index as isize + direction + order.len() as isize) % order.len() as isize;
+    selected.selected = order[new_index as usize];
+}
+
+fn game_over_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut selected: ResMut<SelectedGameOverEntry>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    use GameOverEntry::*;
+    let order = [Continue, ReturnToTitle, SaveReplay];
+
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
+    if direction == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    let current_index = order.iter().position(|s| *s == selected.selected).unwrap_or(0);
+    let new_index = (current_index as isize + direction + order.len() as isize) % order.len() as isize;
+    selected.selected = order[new_index as usize];
+}
+
+fn stage_clear_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut selected: ResMut<SelectedStageClearEntry>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    use StageClearEntry::*;
+    let order = [ReturnToTitle, SaveReplay];
+
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
+    if direction == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    let current_index = order.iter().position(|s| *s == selected.selected).unwrap_or(0);
+    let new_index = (current_index as isize + direction + order.len() as isize) % order.len() as isize;
+    selected.selected = order[new_index as usize];
+}
+
+fn score_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut selected: ResMut<SelectedScorePage>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let num_pages = score_pages().len();
+
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
+    if direction == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    selected.page = ((selected.page as isize + direction + num_pages as isize) % num_pages as isize) as usize;
+}
+
+fn replay_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut list: ResMut<ReplayList>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let num_replays = list.replays.len();
+    if num_replays == 0 {
+        return;
+    }
+    let direction = navigation_direction(&actions, &mut list.repeat_timer, &time.delta());
+    if direction == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    list.selected = ((list.selected as isize + direction + num_replays as isize) % num_replays as isize) as usize;
+}
+
+fn music_room_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut room: ResMut<MusicRoom>,
+    library: Res<MusicLibrary>,
+    libraries: Res<Assets<MusicLibraryAsset>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let num_tracks = libraries.get(&library.0).map(|library| library.tracks.len()).unwrap_or(0);
+    if num_tracks == 0 {
+        return;
+    }
+
+    let direction = navigation_direction(&actions, &mut room.repeat_timer, &time.delta());
+    if direction == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    room.selected = ((room.selected as isize + direction + num_tracks as isize) % num_tracks as isize) as usize;
+}
+
+fn change_option(config: &mut Config, entry: OptionEntry, direction: isize) {
+    const VOLUME_STEP: f32 = 0.1;
+    const MAX_PRACTICE_LIVES: i32 = 8;
+    const DEAD_ZONE_STEP: f32 = 0.05;
+    const MAX_DEAD_ZONE: f32 = 0.9;
+
+    let cycle = |index: usize, len: usize| ((index as isize + direction + len as isize) % len as isize) as usize;
+    match entry {
+        OptionEntry::BgmVolume => {
+            config.bgm_volume = (config.bgm_volume + direction as f32 * VOLUME_STEP).clamp(0.0, 1.0);
+        }
+        OptionEntry::SfxVolume => {
+            config.sfx_volume = (config.sfx_volume + direction as f32 * VOLUME_STEP).clamp(0.0, 1.0);
+        }
+        OptionEntry::Screen => config.fullscreen = !config.fullscreen,
+        OptionEntry::Resolution => {
+            let current = RESOLUTIONS.iter().position(|resolution| *resolution == config.resolution).unwrap_or(0);
+            config.resolution = RESOLUTIONS[cycle(current, RESOLUTIONS.len())];
+        }
+        OptionEntry::Vsync => config.vsync = !config.vsync,
+        OptionEntry::PracticeLives => {
+            config.practice_lives = (config.practice_lives + direction as i32).clamp(0, MAX_PRACTICE_LIVES);
+        }
+        OptionEntry::Hitbox => config.always_show_hitbox = !config.always_show_hitbox,
+        OptionEntry::Language => {
+            let current = Language::ALL.iter().position(|language| *language == config.language).unwrap_or(0);
+            config.language = Language::ALL[cycle(current, Language::ALL.len())];
+        }
+        OptionEntry::Bloom => config.bloom = !config.bloom,
+        OptionEntry::DeadZone => {
+            config.stick_dead_zone = (config.stick_dead_zone + direction as f32 * DEAD_ZONE_STEP).clamp(0.0, MAX_DEAD_ZONE);
+        }
+        OptionEntry::StickSnap => config.stick_snap = !config.stick_snap,
+        OptionEntry::KeyConfig => {}
+    }
+}
+
+fn change_practice(menu: &mut PracticeMenu, entry: PracticeEntry, direction: isize, num_stages: usize) {
+    const MAX_PRACTICE_LIVES: i32 = 8;
+    const MAX_PRACTICE_BOMBS: i32 = 8;
+    const POWER_STEP: i32 = 10;
+    const MAX_PRACTICE_POWER: i32 = 60;
+
+    let cycle = |index: usize, len: usize| ((index as isize + direction + len as isize) % len as isize) as usize;
+    match entry {
+        PracticeEntry::Difficulty => {
+            let difficulties = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Lunatic];
+            let current = difficulties.iter().position(|difficulty| *difficulty == menu.difficulty).unwrap_or(0);
+            menu.difficulty = difficulties[cycle(current, difficulties.len())];
+        }
+        PracticeEntry::Character => {
+            let characters = [Character::ReimuHakurei, Character::MarisaKirisame];
+            let current = characters.iter().position(|character| *character == menu.character).unwrap_or(0);
+            menu.character = characters[cycle(current, characters.len())];
+            menu.spell_card = character_spell_cards(menu.character)[0];
+        }
+        PracticeEntry::SpellCard => {
+            let spell_cards = character_spell_cards(menu.character);
+            let current = spell_cards.iter().position(|spell_card| *spell_card == menu.spell_card).unwrap_or(0);
+            menu.spell_card = spell_cards[cycle(current, spell_cards.len())];
+        }
+        PracticeEntry::Stage => menu.stage = cycle(menu.stage, num_stages.max(1)),
+        PracticeEntry::Lives => menu.lives = (menu.lives + direction as i32).clamp(0, MAX_PRACTICE_LIVES),
+        PracticeEntry::Bombs => menu.bombs = (menu.bombs + direction as i32).clamp(0, MAX_PRACTICE_BOMBS),
+        PracticeEntry::Power => {
+            menu.power = (menu.power + direction as i32 * POWER_STEP).clamp(0, MAX_PRACTICE_POWER);
+        }
+        PracticeEntry::Start | PracticeEntry::SpellPractice => {}
+    }
+}
+
+fn practice_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut menu: ResMut<PracticeMenu>,
+    records: Res<PracticeRecords>,
+    stage_list: Res<StageList>,
+    stage_assets: Res<Assets<StageAsset>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    use PracticeEntry::*;
+    let order = [Difficulty, Character, SpellCard, Stage, Lives, Bombs, Power, Start, SpellPractice];
+
+    let menu = &mut *menu;
+    let direction = navigation_direction(&actions, &mut menu.repeat_timer, &time.delta());
+    let horizontal = horizontal_navigation_direction(&actions, &mut menu.horizontal_timer, &time.delta());
+    if direction == 0 && horizontal == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    if direction != 0 {
+        let current_index = order.iter().position(|s| *s == menu.selected).unwrap_or(0);
+        let new_index = (current_index as isize + direction + order.len() as isize) % order.len() as isize;
+        menu.selected = order[new_index as usize];
+    }
+    if horizontal != 0 {
+        let num_stages = practice_stages(&stage_list, &stage_assets, &records).len();
+        change_practice(menu, menu.selected, horizontal, num_stages);
+    }
+}
+
+fn extra_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut menu: ResMut<ExtraMenu>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let order = [ExtraEntry::Character, ExtraEntry::SpellCard, ExtraEntry::Start];
+
+    let menu = &mut *menu;
+    let direction = navigation_direction(&actions, &mut menu.repeat_timer, &time.delta());
+    let horizontal = horizontal_navigation_direction(&actions, &mut menu.horizontal_timer, &time.delta());
+    if direction == 0 && horizontal == 0 {
+        return;
+    }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    if direction != 0 {
+        let current_index = order.iter().position(|s| *s == menu.selected).unwrap_or(0);
+        let new_index = (current_index as isize + direction + order.len() as isize) % order.len() as isize;
+        menu.selected = order[new_index as usize];
+    }
+    let cycle = |index: usize, len: usize| ((index as isize + horizontal + len as isize) % len as isize) as usize;
+    match menu.selected {
+        ExtraEntry::Character if horizontal != 0 => {
+            let characters = [Character::ReimuHakurei, Character::MarisaKirisame];
+            let current = characters.iter().position(|character| *character == menu.character).unwrap_or(0);
+            menu.character = characters[cycle(current, characters.len())];
+            menu.spell_card = character_spell_cards(menu.character)[0];
+        }
+        ExtraEntry::SpellCard if horizontal != 0 => {
+            let spell_cards = character_spell_cards(menu.character);
+            let current = spell_cards.iter().position(|spell_card| *spell_card == menu.spell_card).unwrap_or(0);
+            menu.spell_card = spell_cards[cycle(current, spell_cards.len())];
+        }
+        _ => {}
+    }
+}
+
+fn spell_practice_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut menu: ResMut<PracticeMenu>,
+    records: Res<PracticeRecords>,
+    stage_list: Res<StageList>,
+    stage_assets: Res<Assets<StageAsset>>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
-    for (entry, mut text) in texts.iter_mut() {
-        let label = text.0.trim_start_matches(['[', 'X', ']', ' ']);
-        if entry.0 == selected.selected {
-            text.0 = format!("[X] {}", label);
-        } else {
-            text.0 = format!("[ ] {}", label);
-        }
+    let stages = practice_stages(&stage_list, &stage_assets, &records);
+    let num_spell_cards = practice_spell_cards(stages).len();
+    if num_spell_cards == 0 {
+        return;
     }
-}
 
-fn main_menu_update_texts(
-    selected: Res<SelectedMenuEntry>,
-    mut texts: Query<(&MainMenuEntry, &mut Text)>,
-) {
-    for (entry, mut text) in texts.iter_mut() {
-        let label = text.0.trim_start_matches(['>', ' ']);
-        if entry.0 == selected.selected {
-            text.0 = format!("> {}", label);
-        } else {
-            text.0 = format!("  {}", label);
-        }
+    let menu = &mut *menu;
+    let direction = navigation_direction(&actions, &mut menu.repeat_timer, &time.delta());
+    if direction == 0 {
+        return;
     }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
+
+    let selected = menu.spell_practice_selected as isize + direction + num_spell_cards as isize;
+    menu.spell_practice_selected = (selected % num_spell_cards as isize) as usize;
 }
 
-fn navigation_direction(
-    keyboard_input: &ButtonInput<KeyCode>,
-    timer: &mut Timer,
-    delta: &std::time::Duration,
-) -> isize {
-    let mut direction = 0;
+fn options_selection(
+    time: Res<Time>,
+    actions: Res<ButtonInput<Action>>,
+    mut selected: ResMut<SelectedOption>,
+    mut config: ResMut<Config>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    use OptionEntry::*;
+    let order = [BgmVolume, SfxVolume, Screen, Resolution, Vsync, PracticeLives, Hitbox, Language, Bloom, DeadZone, StickSnap, KeyConfig];
 
-    if keyboard_input.just_pressed(KeyCode::ArrowUp) {
-        direction = -1;
-        timer.reset();
-    } else if keyboard_input.just_pressed(KeyCode::ArrowDown) {
-        direction = 1;
-        timer.reset();
-    } else if keyboard_input.pressed(KeyCode::ArrowUp) {
-        timer.tick(*delta);
-        if timer.finished() {
-            direction = -1;
-        }
-    } else if keyboard_input.pressed(KeyCode::ArrowDown) {
-        timer.tick(*delta);
-        if timer.finished() {
-            direction = 1;
-        }
-    } else {
-        timer.reset();
+    let selected = &mut *selected;
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
+    let horizontal = horizontal_navigation_direction(&actions, &mut selected.horizontal_timer, &time.delta());
+    if direction == 0 && horizontal == 0 {
+        return;
     }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
 
-    direction
+    if direction != 0 {
+        let current_index = order.iter().position(|s| *s == selected.selected).unwrap_or(0);
+        let new_index = (current_index as isize + direction + order.len() as isize) % order.len() as isize;
+        selected.selected = order[new_index as usize];
+    }
+    if horizontal != 0 {
+        change_option(&mut config, selected.selected, horizontal);
+    }
 }
 
-fn paused_selection(
+fn key_config_selection(
     time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut selected: ResMut<SelectedPauseEntry>,
+    actions: Res<ButtonInput<Action>>,
+    mut screen: ResMut<KeyConfigScreen>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
-    use PausedUiState::*;
-    let order = [Resume, ReturnToTitle];
+    let num_entries = Action::ALL.len() + 1;
 
-    let direction = navigation_direction(&keyboard_input, &mut selected.repeat_timer, &time.delta());
+    let direction = navigation_direction(&actions, &mut screen.repeat_timer, &time.delta());
     if direction == 0 {
         return;
     }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
 
-    let current_index = order.iter().position(|s| *s == selected.selected).unwrap_or(0);
-    let new_index = (current_index as isize + direction + order.len() as isize) % order.len() as isize;
-    selected.selected = order[new_index as usize];
+    screen.selected = ((screen.selected as isize + direction + num_entries as isize) % num_entries as isize) as usize;
+}
+
+fn key_config_capture(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut screen: ResMut<KeyConfigScreen>,
+    mut bindings: ResMut<KeyBindings>,
+    save_dir: Res<SaveDir>,
+) {
+    match screen.capture {
+        KeyCapture::Idle => {}
+        KeyCapture::Bound => screen.capture = KeyCapture::Idle,
+        KeyCapture::Waiting => {
+            // escape or the controller back button keeps the old binding
+            let cancelled = keyboard_input.just_pressed(KeyCode::Escape)
+                || gamepads.iter().any(|gamepad| gamepad.any_just_pressed(gamepad_buttons(Action::Back).iter().copied()));
+            if cancelled {
+                screen.message = "Binding cancelled".to_string();
+                screen.capture = KeyCapture::Bound;
+                return;
+            }
+            let Some(key) = keyboard_input.get_just_pressed().next().copied() else {
+                return;
+            };
+            let action = Action::ALL[screen.selected];
+            let taken_from = bindings.rebind(action, key);
+            screen.message = if taken_from.is_empty() {
+                String::new()
+            } else {
+                let names: Vec<&str> = taken_from.iter().map(Action::name).collect();
+                format!("{} was taken from {}", key_name(key), names.join(", "))
+            };
+            bindings.save(&save_dir);
+            screen.capture = KeyCapture::Bound;
+        }
+    }
+}
+
+fn key_config_idle(screen: Res<KeyConfigScreen>) -> bool {
+    screen.capture == KeyCapture::Idle
 }
 
 fn difficulty_selection(
     time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ButtonInput<Action>>,
     mut selected: ResMut<SelectedDifficulty>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
     use DifficultyState::*;
 
     let order = [Easy, Normal, Hard, Lunatic];
 
-    let direction = navigation_direction(&keyboard_input, &mut selected.repeat_timer, &time.delta());
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
 
     if direction == 0 {
         return;
     }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
 
     let current = selected.selected;
     let current_index = order.iter().position(|s| *s == current).unwrap_or(0);
@@ -916,16 +2752,18 @@ fn difficulty_selection(
 }
 fn confirm_return_to_title_selection(
     time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ButtonInput<Action>>,
     mut selected: ResMut<SelectedConfirmReturnToTitleEntry>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
     use ConfirmReturnToTitleState::*;
     let order = [Confirm, Cancel];
 
-    let direction = navigation_direction(&keyboard_input, &mut selected.repeat_timer, &time.delta());
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
     if direction == 0 {
         return;
     }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
 
     let current_index = order.iter().position(|s| *s == selected.selected).unwrap_or(0);
     let new_index = (current_index as isize + direction + order.len() as isize) % order.len() as isize;
@@ -933,17 +2771,19 @@ fn confirm_return_to_title_selection(
 }
 fn character_selection(
     time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ButtonInput<Action>>,
     mut selected: ResMut<SelectedCharacter>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
     use CharacterState::*;
 
     let order = [ReimuHakurei, MarisaKirisame];
-    let direction = navigation_direction(&keyboard_input, &mut selected.repeat_timer, &time.delta());
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
 
     if direction == 0 {
         return;
     }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
 
     let current = selected.selected;
     let current_index = order.iter().position(|s| *s == current).unwrap_or(0);
@@ -953,12 +2793,14 @@ fn character_selection(
 
 fn main_menu_selection(
     time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ButtonInput<Action>>,
     mut selected: ResMut<SelectedMenuEntry>,
+    clear_records: Res<ClearRecords>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
     use MainMenuState::*;
 
-    let order = [
+    let mut order = vec![
         Start,
         ExtraStart,
         PracticeStart,
@@ -968,12 +2810,17 @@ fn main_menu_selection(
         Option,
         Quit,
     ];
+    // the cursor skips the greyed out entry
+    if !clear_records.any_cleared() {
+        order.retain(|entry| *entry != ExtraStart);
+    }
 
-    let direction = navigation_direction(&keyboard_input, &mut selected.repeat_timer, &time.delta());
+    let direction = navigation_direction(&actions, &mut selected.repeat_timer, &time.delta());
 
     if direction == 0 {
         return;
     }
+    sfx.send(PlaySfx(SoundEffect::MenuMove));
 
     let current = selected.selected;
     let current_index = order.iter().position(|s| *s == current).unwrap_or(0);
@@ -985,12 +2832,16 @@ fn paused_confirm_selection(
     selected: Res<SelectedPauseEntry>,
     mut next_game_state: ResMut<NextState<GameState>>,
     mut next_paused_state: ResMut<NextState<PausedUiState>>,
+    mut retry: EventWriter<RetryPractice>,
+    mut save_replay: EventWriter<SaveReplayRequested>,
 ) {
     match selected.selected {
         PausedUiState::Resume => {
             next_paused_state.set(PausedUiState::Resume);
             next_game_state.set(GameState::Running);
         },
+        PausedUiState::Retry => {retry.send(RetryPractice);}
+        PausedUiState::SaveReplay => {save_replay.send(SaveReplayRequested);}
         PausedUiState::ReturnToTitle => {next_paused_state.set(PausedUiState::ReturnToTitle);}
         _ => {}
     }
@@ -1011,6 +2862,44 @@ fn confirm_return_to_title_confirm_selection(
         }
     }
 }
+fn game_over_confirm_selection(
+    selected: Res<SelectedGameOverEntry>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_main_menu_state: ResMut<NextState<MainMenuState>>,
+    mut game_continued: EventWriter<GameContinued>,
+    mut save_replay: EventWriter<SaveReplayRequested>,
+) {
+    match selected.selected {
+        GameOverEntry::Continue => {
+            game_continued.send(GameContinued);
+            next_game_state.set(GameState::Running);
+        }
+        GameOverEntry::ReturnToTitle => {
+            next_app_state.set(AppState::MainMenu);
+            next_main_menu_state.set(MainMenuState::Choosing);
+        }
+        GameOverEntry::SaveReplay => {
+            save_replay.send(SaveReplayRequested);
+        }
+    }
+}
+fn stage_clear_confirm_selection(
+    selected: Res<SelectedStageClearEntry>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_main_menu_state: ResMut<NextState<MainMenuState>>,
+    mut save_replay: EventWriter<SaveReplayRequested>,
+) {
+    match selected.selected {
+        StageClearEntry::ReturnToTitle => {
+            next_app_state.set(AppState::MainMenu);
+            next_main_menu_state.set(MainMenuState::Choosing);
+        }
+        StageClearEntry::SaveReplay => {
+            save_replay.send(SaveReplayRequested);
+        }
+    }
+}
 fn paused_quit(
     mut next_game_state: ResMut<NextState<GameState>>,
     mut next_paused_state: ResMut<NextState<PausedUiState>>,
@@ -1032,11 +2921,82 @@ fn character_confirm_selection(
 }
 
 fn spell_card_confirm_selection(
+    mut commands: Commands,
+    selected: Res<SelectedSpellCard>,
+    selected_character: Res<SelectedCharacter>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let spell_card = match (selected_character.selected, selected.selected_index) {
+        (CharacterState::ReimuHakurei, 0) => SpellCard::FantasyOrb,
+        (CharacterState::ReimuHakurei, _) => SpellCard::HomingAmulet,
+        (CharacterState::MarisaKirisame, 0) => SpellCard::MasterSpark,
+        (CharacterState::MarisaKirisame, _) => SpellCard::StardustReverie,
+    };
+    let character = match selected_character.selected {
+        CharacterState::ReimuHakurei => Character::ReimuHakurei,
+        CharacterState::MarisaKirisame => Character::MarisaKirisame,
+    };
+    commands.insert_resource(PlayerCharacter(character));
+    commands.insert_resource(PlayerSpellCard(spell_card));
+    next_state.set(AppState::InGame);
+}
+
+fn music_room_confirm_selection(
+    mut room: ResMut<MusicRoom>,
+    mut manager: ResMut<MusicManager>,
+    heard: Res<HeardTracks>,
+    library: Res<MusicLibrary>,
+    libraries: Res<Assets<MusicLibraryAsset>>,
+    tracks: Res<Assets<MusicTrack>>,
+) {
+    let Some(track) = libraries.get(&library.0).and_then(|library| library.tracks.get(room.selected)) else {
+        return;
+    };
+    if !heard.contains(&track.id) {
+        return;
+    }
+    manager.requested = Some(track.id.clone());
+
+    let already_analyzed = room.decoded.as_ref().is_some_and(|(id, _)| *id == track.id)
+        || room.decoding.as_ref().is_some_and(|(id, _)| *id == track.id);
+    if already_analyzed {
+        return;
+    }
+    if let Some(music) = track.source.as_ref().and_then(|source| tracks.get(source)) {
+        let music = music.clone();
+        let task = AsyncComputeTaskPool::get().spawn(async move { DecodedTrack::decode(&music) });
+        room.decoding = Some((track.id.clone(), task));
+    }
+}
+
+fn replay_confirm_selection(
+    mut commands: Commands,
+    list: Res<ReplayList>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
+    let Some((_, replay)) = list.replays.get(list.selected) else {
+        return;
+    };
+    commands.insert_resource(ReplayPlayback::new(replay));
+    commands.insert_resource(PlayerDifficulty(replay.difficulty));
+    commands.insert_resource(PlayerCharacter(replay.character));
+    commands.insert_resource(PlayerSpellCard(replay.spell_card));
     next_state.set(AppState::InGame);
 }
 
+fn end_replay_playback(
+    playback: Res<ReplayPlayback>,
+    game_state: Res<State<GameState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_main_menu_state: ResMut<NextState<MainMenuState>>,
+) {
+    // recording stops at the first game over, so playback ends there too
+    if playback.finished() || *game_state.get() == GameState::GameOver {
+        next_app_state.set(AppState::MainMenu);
+        next_main_menu_state.set(MainMenuState::Replay);
+    }
+}
+
 fn spell_card_quit(
     mut next_state: ResMut<NextState<StartState>>,
 ) {
@@ -1051,22 +3011,28 @@ fn confirm_return_to_title_quit(
 
 
 fn difficulty_confirm_selection(
+    mut commands: Commands,
+    selected: Res<SelectedDifficulty>,
     mut next_state: ResMut<NextState<StartState>>,
 ) {
+    let difficulty = match selected.selected {
+        DifficultyState::Easy => Difficulty::Easy,
+        DifficultyState::Normal => Difficulty::Normal,
+        DifficultyState::Hard => Difficulty::Hard,
+        DifficultyState::Lunatic => Difficulty::Lunatic,
+    };
+    commands.insert_resource(PlayerDifficulty(difficulty));
     next_state.set(StartState::Character);
 }
 
 fn main_menu_confirm_selection(
     selected: Res<SelectedMenuEntry>,
     mut next_state: ResMut<NextState<MainMenuState>>,
-    mut next_app_state: ResMut<NextState<AppState>>,
 ) {
     next_state.set(selected.selected);
     match selected.selected {
         MainMenuState::Choosing => {}
-        MainMenuState::Start => {
-            next_app_state.set(AppState::InGame);
-        }
+        MainMenuState::Start => {}
         MainMenuState::ExtraStart => {}
         MainMenuState::PracticeStart => {}
         MainMenuState::Replay => {}
@@ -1074,6 +3040,9 @@ fn main_menu_confirm_selection(
         MainMenuState::MusicRoom => {}
         MainMenuState::Option => {}
         MainMenuState::Quit => {}
+        MainMenuState::NameEntry => {}
+        MainMenuState::KeyConfig => {}
+        MainMenuState::SpellPractice => {}
     }
 }
 
@@ -1089,6 +3058,237 @@ fn difficulty_quit(
     next_state.set(MainMenuState::Choosing);
 }
 
+fn name_entry_confirm_selection(
+    mut commands: Commands,
+    mut entry: ResMut<NameEntry>,
+    pending: Option<Res<PendingHighScore>>,
+    mut high_scores: ResMut<HighScores>,
+    save_dir: Res<SaveDir>,
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    let grid = name_entry_grid();
+    match grid[entry.row][entry.column] {
+        NameEntryCell::Char(c) => {
+            if entry.name.chars().count() < MAX_NAME_LENGTH {
+                entry.name.push(c);
+            }
+            if entry.name.chars().count() >= MAX_NAME_LENGTH {
+                // jump to END once the name is full
+                entry.row = grid.len() - 1;
+                entry.column = grid[entry.row].len() - 1;
+            }
+        }
+        NameEntryCell::Space => {
+            if entry.name.chars().count() < MAX_NAME_LENGTH {
+                entry.name.push(' ');
+            }
+        }
+        NameEntryCell::Delete => {
+            entry.name.pop();
+        }
+        NameEntryCell::End => {
+            if let Some(pending) = pending {
+                let mut score_entry = pending.entry.clone();
+                score_entry.name = match entry.name.trim() {
+                    "" => "Nanashi".to_string(),
+                    name => name.to_string(),
+                };
+                high_scores.insert(pending.difficulty, pending.character, score_entry);
+                high_scores.save(&save_dir);
+            }
+            commands.remove_resource::<PendingHighScore>();
+            next_state.set(MainMenuState::Choosing);
+        }
+    }
+}
+
+fn name_entry_delete(
+    mut entry: ResMut<NameEntry>,
+) {
+    entry.name.pop();
+}
+
+fn redirect_to_name_entry(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    next_state.set(MainMenuState::NameEntry);
+}
+
+fn score_quit(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    next_state.set(MainMenuState::Choosing);
+}
+
+fn start_practice(
+    commands: &mut Commands,
+    menu: &PracticeMenu,
+    target: PracticeTarget,
+    next_state: &mut NextState<AppState>,
+) {
+    commands.insert_resource(PlayerDifficulty(menu.difficulty));
+    commands.insert_resource(PlayerCharacter(menu.character));
+    commands.insert_resource(PlayerSpellCard(menu.spell_card));
+    commands.insert_resource(PracticeRun {
+        target,
+        lives: menu.lives,
+        bombs: menu.bombs,
+        power: menu.power,
+    });
+    next_state.set(AppState::InGame);
+}
+
+fn practice_confirm_selection(
+    mut commands: Commands,
+    mut menu: ResMut<PracticeMenu>,
+    records: Res<PracticeRecords>,
+    stage_list: Res<StageList>,
+    stage_assets: Res<Assets<StageAsset>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_main_menu_state: ResMut<NextState<MainMenuState>>,
+) {
+    let num_stages = practice_stages(&stage_list, &stage_assets, &records).len();
+    match menu.selected {
+        PracticeEntry::Start => {
+            if menu.stage < num_stages {
+                start_practice(&mut commands, &menu, PracticeTarget::Stage(menu.stage), &mut next_app_state);
+            }
+        }
+        PracticeEntry::SpellPractice => next_main_menu_state.set(MainMenuState::SpellPractice),
+        entry => change_practice(&mut menu, entry, 1, num_stages),
+    }
+}
+
+fn spell_practice_confirm_selection(
+    mut commands: Commands,
+    menu: Res<PracticeMenu>,
+    records: Res<PracticeRecords>,
+    stage_list: Res<StageList>,
+    stage_assets: Res<Assets<StageAsset>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let stages = practice_stages(&stage_list, &stage_assets, &records);
+    let spell_cards = practice_spell_cards(stages);
+    let Some(spell_card) = spell_cards.get(menu.spell_practice_selected) else {
+        return;
+    };
+    // only cards met at least once can be picked
+    let met = records
+        .spell_card(&spell_card.name, menu.difficulty, menu.character)
+        .is_some_and(|record| record.attempts > 0);
+    if !met {
+        return;
+    }
+    let target = PracticeTarget::SpellCard {
+        stage: spell_card.stage,
+        boss: spell_card.boss,
+        phase: spell_card.phase,
+    };
+    start_practice(&mut commands, &menu, target, &mut next_state);
+}
+
+/// a finished practice section goes back to the list it was picked from
+fn finish_practice(
+    practice: Res<PracticeRun>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_main_menu_state: ResMut<NextState<MainMenuState>>,
+) {
+    next_app_state.set(AppState::MainMenu);
+    next_main_menu_state.set(match practice.target {
+        PracticeTarget::Stage(_) => MainMenuState::PracticeStart,
+        PracticeTarget::SpellCard { .. } => MainMenuState::SpellPractice,
+    });
+}
+
+fn extra_confirm_selection(
+    mut commands: Commands,
+    menu: Res<ExtraMenu>,
+    clear_records: Res<ClearRecords>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if menu.selected != ExtraEntry::Start || !clear_records.is_cleared(menu.character) {
+        return;
+    }
+    commands.insert_resource(PlayerDifficulty(Difficulty::Extra));
+    commands.insert_resource(PlayerCharacter(menu.character));
+    commands.insert_resource(PlayerSpellCard(menu.spell_card));
+    next_state.set(AppState::InGame);
+}
+
+fn extra_quit(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    next_state.set(MainMenuState::Choosing);
+}
+
+fn practice_quit(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    next_state.set(MainMenuState::Choosing);
+}
+
+fn spell_practice_quit(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    next_state.set(MainMenuState::PracticeStart);
+}
+
+fn options_confirm_selection(
+    selected: Res<SelectedOption>,
+    mut config: ResMut<Config>,
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    if selected.selected == OptionEntry::KeyConfig {
+        next_state.set(MainMenuState::KeyConfig);
+        return;
+    }
+    change_option(&mut config, selected.selected, 1);
+}
+
+fn options_quit(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+    config: Res<Config>,
+    save_dir: Res<SaveDir>,
+) {
+    config.save(&save_dir);
+    next_state.set(MainMenuState::Choosing);
+}
+
+fn key_config_confirm_selection(
+    mut screen: ResMut<KeyConfigScreen>,
+    mut bindings: ResMut<KeyBindings>,
+    save_dir: Res<SaveDir>,
+) {
+    if screen.selected < Action::ALL.len() {
+        screen.capture = KeyCapture::Waiting;
+        screen.message.clear();
+    } else {
+        *bindings = KeyBindings::default();
+        bindings.save(&save_dir);
+        screen.message = "Keys reset to default".to_string();
+    }
+}
+
+fn key_config_quit(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    next_state.set(MainMenuState::Option);
+}
+
+fn music_room_quit(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+    mut manager: ResMut<MusicManager>,
+) {
+    manager.requested = None;
+    next_state.set(MainMenuState::Choosing);
+}
+
+fn replay_quit(
+    mut next_state: ResMut<NextState<MainMenuState>>,
+) {
+    next_state.set(MainMenuState::Choosing);
+}
+
 fn character_quit(
     mut next_state: ResMut<NextState<StartState>>
 ) {
@@ -1104,7 +3304,7 @@ fn main_menu_quit(
 use bevy::app::AppExit;
 use bevy::color::palettes::css::PINK;
 use bevy::color::palettes::tailwind::*;
-use crate::ascii_animation::{spawn_ascii_animation, AsciiAnimationAsset, MainMenuAnimation};
+use crate::ascii_animation::{spawn_ascii_animation, AsciiAnimation, AsciiAnimationAsset, MainMenuAnimation, PortraitSide, Portraits};
 
 fn main_menu_handle_quit(
     mut exit_writer: EventWriter<AppExit>,
@@ -1112,12 +3312,18 @@ fn main_menu_handle_quit(
     exit_writer.send(AppExit::Success);
 }
 
-fn back_key_just_pressed(input: Res<ButtonInput<KeyCode>>) -> bool {
-    input.just_pressed(KeyCode::KeyX) || input.just_pressed(KeyCode::Escape)
+fn play_menu_sfx(effect: SoundEffect) -> impl FnMut(EventWriter<PlaySfx>) {
+    move |mut sfx: EventWriter<PlaySfx>| {
+        sfx.send(PlaySfx(effect));
+    }
+}
+
+fn back_key_just_pressed(input: Res<ButtonInput<Action>>) -> bool {
+    input.just_pressed(Action::Back)
 }
 
-fn confirm_key_just_pressed(input: Res<ButtonInput<KeyCode>>) -> bool {
-    input.just_pressed(KeyCode::KeyZ) || input.just_pressed(KeyCode::Enter)
+fn confirm_key_just_pressed(input: Res<ButtonInput<Action>>) -> bool {
+    input.just_pressed(Action::Confirm)
 }
 
 pub struct GameUiPlugin;
@@ -1135,13 +3341,31 @@ impl Plugin for GameUiPlugin {
             .enable_state_scoped_entities::<MainMenuState>()
             .enable_state_scoped_entities::<StartState>()
             .enable_state_scoped_entities::<ConfirmReturnToTitleState>()
-            .add_systems(OnEnter(MainMenuState::Choosing), setup_main_menu)
+            .add_systems(OnEnter(MainMenuState::Choosing), (
+                setup_main_menu.run_if(not(resource_exists::<PendingHighScore>)),
+                redirect_to_name_entry.run_if(resource_exists::<PendingHighScore>),
+            ))
+            .add_systems(OnEnter(MainMenuState::NameEntry), setup_name_entry)
             .add_systems(OnEnter(AppState::InGame), setup_in_game_ui)
             .add_systems(OnEnter(StartState::SpellCard), setup_spell_cards)
             .add_systems(OnEnter(StartState::Difficulty), setup_difficulty)
             .add_systems(OnEnter(StartState::Character), setup_character)
             .add_systems(OnEnter(MainMenuState::Start), setup_start)
+            .add_systems(OnEnter(MainMenuState::Score), setup_score)
+            .add_systems(OnEnter(MainMenuState::Replay), setup_replay)
+            .add_systems(OnEnter(MainMenuState::MusicRoom), setup_music_room)
+            .add_systems(OnEnter(MainMenuState::Option), setup_options)
+            .add_systems(OnEnter(MainMenuState::KeyConfig), setup_key_config)
+            .add_systems(OnEnter(MainMenuState::ExtraStart), setup_extra)
+            .add_systems(OnEnter(MainMenuState::PracticeStart), setup_practice)
+            .add_systems(OnEnter(MainMenuState::SpellPractice), setup_spell_practice)
+            .add_event::<GameContinued>()
+            .add_event::<PlaySfx>()
             .add_systems(OnEnter(GameState::Paused), setup_paused)
+            .add_systems(OnEnter(GameState::GameOver), setup_game_over)
+            .add_systems(OnEnter(GameState::StageClear), setup_stage_clear)
+            .add_systems(OnEnter(AppState::InGame), setup_fade_overlay)
+            .add_systems(OnEnter(GameState::Dialogue), setup_dialogue)
             .add_systems(OnEnter(PausedUiState::ReturnToTitle), setup_confirm_return_to_title)
             .add_systems(OnExit(PausedUiState::ReturnToTitle), cleanup_confirm_return_to_title)
             .add_systems(Update, (
@@ -1149,7 +3373,7 @@ impl Plugin for GameUiPlugin {
                 main_menu_confirm_selection.run_if(confirm_key_just_pressed),
                 main_menu_update_texts.run_if(resource_changed::<SelectedMenuEntry>),
                 main_menu_reset_selection.run_if(back_key_just_pressed),
-                main_menu_quit.run_if(input_just_pressed(KeyCode::KeyQ)),
+                main_menu_quit.run_if(input_just_pressed(Action::Quit)),
             ).run_if(in_state(MainMenuState::Choosing)))
             .add_systems(Update, (
                 difficulty_selection,
@@ -1181,6 +3405,86 @@ impl Plugin for GameUiPlugin {
                 confirm_return_to_title_confirm_selection.run_if(confirm_key_just_pressed),
                 confirm_return_to_title_quit.run_if(back_key_just_pressed),
             ).run_if(in_state(PausedUiState::ReturnToTitle)))
+            .add_systems(Update, (
+                name_entry_selection,
+                name_entry_update_texts,
+                name_entry_confirm_selection.run_if(confirm_key_just_pressed),
+                name_entry_delete.run_if(back_key_just_pressed),
+            ).run_if(in_state(MainMenuState::NameEntry)))
+            .add_systems(Update, (
+                score_selection,
+                score_update_texts,
+                score_quit.run_if(back_key_just_pressed),
+            ).run_if(in_state(MainMenuState::Score)))
+            .add_systems(Update, (
+                replay_selection,
+                replay_update_texts,
+                replay_confirm_selection.run_if(confirm_key_just_pressed),
+                replay_quit.run_if(back_key_just_pressed),
+            ).run_if(in_state(MainMenuState::Replay)))
+            .add_systems(Update, (
+                music_room_selection,
+                music_room_update_texts,
+                music_room_spectrum,
+                music_room_confirm_selection.run_if(confirm_key_just_pressed),
+                music_room_quit.run_if(back_key_just_pressed),
+            ).run_if(in_state(MainMenuState::MusicRoom)))
+            .add_systems(Update, (
+                options_selection,
+                options_update_texts,
+                options_confirm_selection.run_if(confirm_key_just_pressed),
+                options_quit.run_if(back_key_just_pressed),
+            ).run_if(in_state(MainMenuState::Option)))
+            .add_systems(Update, (
+                key_config_capture,
+                (
+                    key_config_selection,
+                    key_config_confirm_selection.run_if(confirm_key_just_pressed),
+                    key_config_quit.run_if(back_key_just_pressed),
+                ).run_if(key_config_idle),
+                key_config_update_texts,
+            ).chain().run_if(in_state(MainMenuState::KeyConfig)))
+            .add_systems(Update, (
+                extra_selection,
+                extra_update_texts,
+                extra_confirm_selection.run_if(confirm_key_just_pressed),
+                extra_quit.run_if(back_key_just_pressed),
+            ).run_if(in_state(MainMenuState::ExtraStart)))
+            .add_systems(Update, (
+                practice_selection,
+                practice_update_texts,
+                practice_confirm_selection.run_if(confirm_key_just_pressed),
+                practice_quit.run_if(back_key_just_pressed),
+            ).run_if(in_state(MainMenuState::PracticeStart)))
+            .add_systems(Update, (
+                spell_practice_selection,
+                spell_practice_update_texts,
+                spell_practice_confirm_selection.run_if(confirm_key_just_pressed),
+                spell_practice_quit.run_if(back_key_just_pressed),
+            ).run_if(in_state(MainMenuState::SpellPractice)))
+            .add_systems(Update, finish_practice
+                .run_if(in_state(AppState::InGame).and(resource_exists::<PracticeRun>).and(on_event::<StageFinished>)))
+            .add_systems(Update, update_fade_overlay.run_if(in_state(AppState::InGame).and(resource_changed::<ScreenFade>)))
+            .add_systems(Update, (
+                dialogue_update_texts,
+                dialogue_portraits,
+            ).run_if(in_state(GameState::Dialogue).and(resource_exists::<ActiveDialogue>)))
+            .add_systems(Update, end_replay_playback
+                .run_if(in_state(AppState::InGame).and(resource_exists::<ReplayPlayback>)))
+            .add_systems(Update, (
+                game_over_selection,
+                game_over_update_texts,
+                game_over_confirm_selection.run_if(confirm_key_just_pressed),
+            ).run_if(in_state(GameState::GameOver)))
+            .add_systems(Update, (
+                stage_clear_selection,
+                stage_clear_update_texts,
+                stage_clear_confirm_selection.run_if(confirm_key_just_pressed),
+            ).run_if(in_state(GameState::StageClear)))
+            .add_systems(Update, (
+                play_menu_sfx(SoundEffect::MenuConfirm).run_if(confirm_key_just_pressed),
+                play_menu_sfx(SoundEffect::MenuCancel).run_if(back_key_just_pressed),
+            ).run_if(in_state(AppState::MainMenu).or(in_state(GameState::Paused)).or(in_state(GameState::GameOver)).or(in_state(GameState::StageClear))))
             .add_systems(OnEnter(MainMenuState::Quit), main_menu_handle_quit)   ;
     }
 }
\ No newline at end of file
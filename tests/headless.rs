@@ -0,0 +1,246 @@
+use asciihou::character::Character;
+use asciihou::dialogue::ActiveDialogue;
+use asciihou::difficulty::{Difficulty, PlayerDifficulty};
+use asciihou::extra::ClearRecords;
+use asciihou::event::PlayerHit;
+use asciihou::game::{Boss, Enemy, Health, Player, PlayerLives, Score, StageRunner};
+use asciihou::headless::{headless_app, start_run, step_ticks};
+use asciihou::input::InputFrame;
+use asciihou::practice::{PracticeRun, PracticeTarget, RetryPractice};
+use asciihou::replay::{ReplayFile, ReplayPlayback, REPLAY_VERSION};
+use asciihou::stage::StageFinished;
+use asciihou::state::{AppState, GameState};
+use bevy::prelude::*;
+
+fn scripted_run(seed: u64, inputs: Vec<(u8, u32)>) -> App {
+    let replay = ReplayFile {
+        version: REPLAY_VERSION,
+        date: String::new(),
+        seed,
+        tick_rate: 60.0,
+        character: default(),
+        difficulty: default(),
+        spell_card: default(),
+        inputs,
+    };
+    let mut app = headless_app(seed);
+    app.insert_resource(ReplayPlayback::new(&replay));
+    start_run(&mut app);
+    app
+}
+
+fn practice_run(target: PracticeTarget) -> App {
+    let mut app = headless_app(1);
+    app.insert_resource(PracticeRun {
+        target,
+        lives: 5,
+        bombs: 1,
+        power: 40,
+    });
+    start_run(&mut app);
+    app
+}
+
+fn press_key(app: &mut App, key: KeyCode) {
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+    app.update();
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(key);
+    app.update();
+}
+
+fn enemy_positions(app: &mut App) -> Vec<(i32, i32)> {
+    let mut positions: Vec<(i32, i32)> = app.world_mut()
+        .query_filtered::<&Transform, With<Enemy>>()
+        .iter(app.world())
+        .map(|transform| (transform.translation.x.round() as i32, transform.translation.y.round() as i32))
+        .collect();
+    positions.sort();
+    positions
+}
+
+#[test]
+fn stage_clock_follows_fixed_ticks() {
+    let mut app = scripted_run(1, vec![]);
+    step_ticks(&mut app, 120);
+
+    let elapsed = app.world().resource::<StageRunner>().elapsed;
+    assert!((elapsed - 2.0).abs() < 0.05, "elapsed {elapsed}");
+}
+
+#[test]
+fn shooting_the_first_wave_scores() {
+    let mut app = scripted_run(1, vec![(InputFrame::SHOOT, 900)]);
+    step_ticks(&mut app, 900);
+
+    assert!(app.world().resource::<Score>().0 > 0);
+}
+
+#[test]
+fn same_seed_and_inputs_give_same_state() {
+    let inputs = vec![
+        (InputFrame::SHOOT, 300),
+        (InputFrame::SHOOT | InputFrame::RIGHT, 120),
+        (InputFrame::SHOOT | InputFrame::FOCUS | InputFrame::UP, 180),
+        (InputFrame::BOMB, 1),
+        (InputFrame::SHOOT | InputFrame::LEFT, 600),
+    ];
+    let mut first = scripted_run(42, inputs.clone());
+    let mut second = scripted_run(42, inputs);
+    step_ticks(&mut first, 1200);
+    step_ticks(&mut second, 1200);
+
+    assert_eq!(first.world().resource::<Score>().0, second.world().resource::<Score>().0);
+    assert_eq!(first.world().resource::<PlayerLives>().0, second.world().resource::<PlayerLives>().0);
+    assert_eq!(enemy_positions(&mut first), enemy_positions(&mut second));
+
+    let player_position = |app: &mut App| {
+        let transform = app.world_mut()
+            .query_filtered::<&Transform, With<Player>>()
+            .single(app.world());
+        transform.translation
+    };
+    assert_eq!(player_position(&mut first), player_position(&mut second));
+}
+
+#[test]
+fn paused_game_does_not_advance() {
+    let mut app = scripted_run(1, vec![]);
+    step_ticks(&mut app, 60);
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Paused);
+    app.update();
+
+    let elapsed = app.world().resource::<StageRunner>().elapsed;
+    step_ticks(&mut app, 120);
+    assert_eq!(app.world().resource::<StageRunner>().elapsed, elapsed);
+}
+
+#[test]
+fn spell_practice_starts_at_the_boss() {
+    let mut app = practice_run(PracticeTarget::SpellCard { stage: 0, boss: 0, phase: 1 });
+    step_ticks(&mut app, 10);
+
+    assert_eq!(app.world().resource::<StageRunner>().current_boss(), Some(0));
+    assert_eq!(enemy_positions(&mut app).len(), 1);
+    assert_eq!(app.world().resource::<PlayerLives>().0, 5);
+}
+
+#[test]
+fn a_death_during_a_spell_card_loses_the_capture_even_if_lives_come_back() {
+    let finish_spell_card = |die: bool| {
+        let mut app = practice_run(PracticeTarget::SpellCard { stage: 0, boss: 0, phase: 1 });
+        step_ticks(&mut app, 10);
+        if die {
+            let player = app.world_mut().query_filtered::<Entity, With<Player>>().single(app.world());
+            app.world_mut().send_event(PlayerHit { entity: player, position: Vec2::ZERO });
+            step_ticks(&mut app, 1);
+            // a continue or an extend refills the lives before the card ends
+            app.world_mut().resource_mut::<PlayerLives>().0 = 5;
+        }
+        let mut health = app.world_mut().query_filtered::<&mut Health, With<Boss>>();
+        health.single_mut(app.world_mut()).0 = 0;
+        step_ticks(&mut app, 2);
+        app.world().resource::<Score>().0
+    };
+
+    let captured = finish_spell_card(false);
+    let failed = finish_spell_card(true);
+    assert!(captured >= failed + 50000, "captured {captured}, failed {failed}");
+}
+
+#[test]
+fn retry_restarts_the_practice_section() {
+    let mut app = practice_run(PracticeTarget::Stage(0));
+    step_ticks(&mut app, 180);
+    assert!(!enemy_positions(&mut app).is_empty());
+
+    // one update out of the run and one back in
+    app.world_mut().send_event(RetryPractice);
+    app.update();
+    assert_eq!(*app.world().resource::<State<AppState>>().get(), AppState::Restarting);
+    app.update();
+    assert_eq!(*app.world().resource::<State<AppState>>().get(), AppState::InGame);
+
+    assert!(app.world().resource::<StageRunner>().elapsed < 0.1);
+    assert!(enemy_positions(&mut app).is_empty());
+    assert_eq!(app.world().resource::<PlayerLives>().0, 5);
+}
+
+#[test]
+fn extra_difficulty_plays_the_extra_stage() {
+    // a replay goes straight past the stage 1 intro dialogue
+    let mut regular = scripted_run(1, vec![]);
+    let mut extra = headless_app(1);
+    extra.insert_resource(PlayerDifficulty(Difficulty::Extra));
+    start_run(&mut extra);
+    step_ticks(&mut regular, 150);
+    step_ticks(&mut extra, 150);
+
+    assert!(extra.world().resource::<StageRunner>().extra);
+    assert!(!enemy_positions(&mut regular).is_empty());
+    assert!(!enemy_positions(&mut extra).is_empty());
+    assert_ne!(enemy_positions(&mut regular), enemy_positions(&mut extra));
+}
+
+#[test]
+fn clearing_the_last_stage_unlocks_the_extra_stage() {
+    let mut app = headless_app(1);
+    start_run(&mut app);
+    assert!(!app.world().resource::<ClearRecords>().any_cleared());
+
+    app.world_mut().send_event(StageFinished { stage: 0 });
+    step_ticks(&mut app, 1);
+
+    assert!(app.world().resource::<ClearRecords>().is_cleared(Character::ReimuHakurei));
+}
+
+#[test]
+fn finishing_the_stage_of_a_regular_run_shows_the_clear_screen() {
+    let mut app = headless_app(1);
+    start_run(&mut app);
+    press_key(&mut app, KeyCode::ControlLeft);
+    step_ticks(&mut app, 1);
+    assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Running);
+
+    app.world_mut().resource_mut::<StageRunner>().finished = true;
+    step_ticks(&mut app, 1);
+    app.update();
+
+    assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::StageClear);
+}
+
+#[test]
+fn intro_dialogue_holds_the_stage_until_skipped() {
+    let mut app = headless_app(1);
+    start_run(&mut app);
+    step_ticks(&mut app, 60);
+
+    assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Dialogue);
+    let elapsed = app.world().resource::<StageRunner>().elapsed;
+    assert!(elapsed < 0.1, "elapsed {elapsed}");
+
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::ControlLeft);
+    app.update();
+    step_ticks(&mut app, 60);
+
+    assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Running);
+    assert!(app.world().resource::<StageRunner>().elapsed > elapsed + 0.9);
+}
+
+#[test]
+fn dialogue_lines_change_portrait_expressions() {
+    let mut app = headless_app(1);
+    start_run(&mut app);
+    step_ticks(&mut app, 2);
+    let expression = |app: &App| app.world().resource::<ActiveDialogue>().expression("Reimu").map(str::to_string);
+    assert_eq!(expression(&app), None);
+
+    // a speaker shows up neutral, then lines and directions pick the clip
+    press_key(&mut app, KeyCode::KeyZ);
+    assert_eq!(expression(&app).as_deref(), Some("neutral"));
+    press_key(&mut app, KeyCode::KeyZ);
+    press_key(&mut app, KeyCode::KeyZ);
+    assert_eq!(expression(&app).as_deref(), Some("surprised"));
+    press_key(&mut app, KeyCode::KeyZ);
+    press_key(&mut app, KeyCode::KeyZ);
+    assert_eq!(expression(&app).as_deref(), Some("annoyed"));
+}
@@ -0,0 +1,37 @@
+use asciihou::input::{stick_directions, Action, KeyBindings};
+use bevy::input::keyboard::KeyCode;
+use bevy::math::Vec2;
+
+#[test]
+fn stick_inside_the_dead_zone_is_ignored() {
+    assert!(stick_directions(Vec2::new(0.1, -0.15), 0.25, true).is_empty());
+    assert!(stick_directions(Vec2::new(0.1, -0.15), 0.25, false).is_empty());
+}
+
+#[test]
+fn snapping_rounds_the_stick_to_eight_directions() {
+    // a shallow angle snaps to a straight direction, a steep diagonal keeps both axes
+    assert_eq!(stick_directions(Vec2::new(0.9, 0.3), 0.25, true), vec![Action::Right]);
+    assert_eq!(stick_directions(Vec2::new(-0.6, -0.5), 0.25, true), vec![Action::Down, Action::Left]);
+    // without snapping each axis past the dead zone counts
+    assert_eq!(stick_directions(Vec2::new(0.9, 0.3), 0.25, false), vec![Action::Up, Action::Right]);
+}
+
+#[test]
+fn rebinding_takes_the_key_from_every_conflicting_action() {
+    let mut bindings = KeyBindings::default();
+    let taken_from = bindings.rebind(Action::Up, KeyCode::KeyZ);
+
+    // Z is shared by a gameplay and a menu action, both clash with Up
+    assert_eq!(taken_from, vec![Action::Shoot, Action::Confirm]);
+    assert_eq!(bindings.keys(Action::Up), &[KeyCode::KeyZ]);
+    assert_eq!(bindings.keys(Action::Confirm), &[KeyCode::Enter]);
+    assert_eq!(bindings.keys(Action::Shoot), &[KeyCode::ArrowUp]);
+    for action in Action::ALL {
+        for other in Action::ALL {
+            if action.conflicts_with(other) {
+                assert!(bindings.keys(action).iter().all(|key| !bindings.keys(other).contains(key)), "{action:?} and {other:?} share a key");
+            }
+        }
+    }
+}
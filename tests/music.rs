@@ -0,0 +1,61 @@
+use asciihou::music::{DecodedTrack, MusicLibraryAsset, MusicTrack};
+use bevy::audio::AudioSource;
+
+fn sine(freq: f32, sample_rate: u32, secs: f32) -> DecodedTrack {
+    let samples = (0..(sample_rate as f32 * secs) as usize)
+        .map(|i| (std::f32::consts::TAU * freq * i as f32 / sample_rate as f32).sin() * 0.5)
+        .collect();
+    DecodedTrack {
+        samples,
+        sample_rate,
+        loop_start: 0,
+        loop_end: None,
+    }
+}
+
+#[test]
+fn playback_position_wraps_inside_the_loop() {
+    let mut track = sine(440.0, 1000, 10.0);
+    track.loop_start = 2000;
+    track.loop_end = Some(6000);
+
+    assert_eq!(track.frame_at(1.5), 1500);
+    assert_eq!(track.frame_at(5.0), 5000);
+    assert_eq!(track.frame_at(6.5), 2500);
+    assert_eq!(track.frame_at(11.0), 3000);
+}
+
+#[test]
+fn spectrum_peaks_at_the_played_frequency() {
+    let bands = 32;
+    let low = sine(100.0, 44100, 1.0).spectrum(0.2, bands);
+    let high = sine(5000.0, 44100, 1.0).spectrum(0.2, bands);
+
+    let loudest = |levels: &[f32]| levels
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(band, _)| band)
+        .unwrap();
+    assert!(loudest(&low) < loudest(&high));
+    assert!(low[loudest(&low)] > 0.5);
+}
+
+#[test]
+fn every_track_in_the_library_decodes_past_its_loop() {
+    let library = std::fs::read("assets/audio/music.bgm.ron").unwrap();
+    let library = ron::de::from_bytes::<MusicLibraryAsset>(&library).unwrap();
+    for track in &library.tracks {
+        let bytes = std::fs::read(format!("assets/{}", track.path))
+            .unwrap_or_else(|err| panic!("{}: {}", track.path, err));
+        let decoded = DecodedTrack::decode(&MusicTrack {
+            audio: AudioSource { bytes: bytes.into() },
+            loop_start: track.loop_start,
+            loop_end: track.loop_end,
+        });
+        let end = track.loop_end.unwrap_or(decoded.samples.len() as u64);
+        assert!(track.loop_start < end, "{} loops backwards", track.id);
+        assert!(end as usize <= decoded.samples.len(), "{} loops past its end", track.id);
+    }
+    assert!(library.track(&library.menu).is_some());
+}
@@ -0,0 +1,14 @@
+use asciihou::sfx::SoundBankAsset;
+use bevy::audio::{AudioSource, Decodable};
+
+#[test]
+fn every_sound_in_the_bank_decodes() {
+    let bank = std::fs::read("assets/audio/sound_bank.sfx.ron").unwrap();
+    let bank = ron::de::from_bytes::<SoundBankAsset>(&bank).unwrap();
+    for sound in bank.sounds.values() {
+        let bytes = std::fs::read(format!("assets/{}", sound.path))
+            .unwrap_or_else(|err| panic!("{}: {}", sound.path, err));
+        let source = AudioSource { bytes: bytes.into() };
+        assert!(source.decoder().count() > 0, "{} decodes to silence", sound.path);
+    }
+}